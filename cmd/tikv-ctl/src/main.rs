@@ -137,6 +137,7 @@ fn new_debug_executor(
         };
         let mut raft_db = RocksEngine::from_db(Arc::new(raft_db));
         raft_db.set_shared_block_cache(shared_block_cache);
+        raft_db.set_raft_log_checksum(cfg.raftdb.enable_log_checksum);
         let debugger = Debugger::new(Engines::new(kv_db, raft_db), cfg_controller);
         Box::new(debugger) as Box<dyn DebugExecutor>
     } else {
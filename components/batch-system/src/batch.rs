@@ -8,6 +8,7 @@
 use crate::config::Config;
 use crate::fsm::{Fsm, FsmScheduler, Priority};
 use crate::mailbox::BasicMailbox;
+use crate::metrics::{FSM_RESCHEDULE_COUNTER, POLLER_BUSY_SECONDS, POLLER_IDLE_SECONDS};
 use crate::router::Router;
 use crossbeam::channel::{self, SendError};
 use file_system::{set_io_type, IOType};
@@ -17,7 +18,7 @@ use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 use tikv_util::mpsc;
-use tikv_util::time::Instant;
+use tikv_util::time::{duration_to_sec, Instant};
 use tikv_util::{debug, error, info, safe_panic, thd_name, warn};
 
 /// A unify type for FSMs so that they can be sent to channel easily.
@@ -171,6 +172,7 @@ impl<N: Fsm, C: Fsm> Batch<N, C> {
     pub fn reschedule(&mut self, router: &BatchRouter<N, C>, index: usize) {
         let fsm = self.normals.swap_remove(index);
         self.timers.swap_remove(index);
+        FSM_RESCHEDULE_COUNTER.with_label_values(&["normal"]).inc();
         router.normal_scheduler.schedule(fsm);
     }
 
@@ -253,6 +255,12 @@ struct Poller<N: Fsm, C: Fsm, Handler> {
     handler: Handler,
     max_batch_size: usize,
     reschedule_duration: Duration,
+    slow_handle_threshold: Duration,
+    poll_budget: Duration,
+    // Per-pool time accounting, see `POLLER_BUSY_SECONDS` and
+    // `POLLER_IDLE_SECONDS`.
+    busy_time: prometheus::Counter,
+    idle_time: prometheus::Counter,
 }
 
 enum ReschedulePolicy {
@@ -262,6 +270,48 @@ enum ReschedulePolicy {
 }
 
 impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
+    /// Starts a timer for the slow handle log, or `None` when it's disabled.
+    #[inline]
+    fn slow_handle_timer(&self) -> Option<Instant> {
+        if self.slow_handle_threshold > Duration::from_secs(0) {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    /// Warns about the handle call if it exceeded the configured threshold.
+    /// `len` is the channel length observed by the handler, which points at
+    /// how much work is still queued behind the slow FSM.
+    #[inline]
+    fn log_slow_handle<T>(&self, fsm_type: &str, fsm: &T, timer: Option<Instant>, len: Option<usize>) {
+        if let Some(start) = timer {
+            let elapsed = start.saturating_elapsed();
+            if elapsed >= self.slow_handle_threshold {
+                warn!(
+                    "handling {} fsm {:p} is too slow: takes {:?}, channel len {:?}",
+                    fsm_type, fsm, elapsed, len
+                );
+            }
+        }
+    }
+
+    /// Starts a timer for the poll budget, or `None` when it's unbounded.
+    #[inline]
+    fn poll_budget_timer(&self) -> Option<Instant> {
+        if self.poll_budget > Duration::from_secs(0) {
+            Some(Instant::now_coarse())
+        } else {
+            None
+        }
+    }
+
+    /// Whether the budget of the current round is used up.
+    #[inline]
+    fn over_poll_budget(&self, round_timer: Option<Instant>) -> bool {
+        round_timer.map_or(false, |t| t.saturating_elapsed() >= self.poll_budget)
+    }
+
     fn fetch_fsm(&mut self, batch: &mut Batch<N, C>) -> bool {
         if batch.control.is_some() {
             return true;
@@ -273,7 +323,11 @@ impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
 
         if batch.is_empty() {
             self.handler.pause();
-            if let Ok(fsm) = self.fsm_receiver.recv() {
+            let idle_timer = Instant::now();
+            let fetched = self.fsm_receiver.recv();
+            self.idle_time
+                .inc_by(duration_to_sec(idle_timer.saturating_elapsed()));
+            if let Ok(fsm) = fetched {
                 return batch.push(fsm);
             }
         }
@@ -294,10 +348,14 @@ impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
             // max size of batch. It's helpful to protect regions from becoming hungry
             // if some regions are hot points.
             let max_batch_size = std::cmp::max(self.max_batch_size, batch.normals.len());
+            let round_timer = self.poll_budget_timer();
+            let busy_timer = Instant::now();
             self.handler.begin(max_batch_size);
 
             if batch.control.is_some() {
+                let timer = self.slow_handle_timer();
                 let len = self.handler.handle_control(batch.control.as_mut().unwrap());
+                self.log_slow_handle("control", &**batch.control.as_ref().unwrap(), timer, len);
                 if batch.control.as_ref().unwrap().is_stopped() {
                     batch.remove_control(&self.router.control_box);
                 } else if let Some(len) = len {
@@ -307,7 +365,15 @@ impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
 
             let mut hot_fsm_count = 0;
             for (i, p) in batch.normals.iter_mut().enumerate() {
+                if self.over_poll_budget(round_timer) {
+                    // The round's budget is used up, hand the unhandled FSMs
+                    // back to the scheduler so other pollers can take over.
+                    reschedule_fsms.push((i, ReschedulePolicy::Schedule));
+                    continue;
+                }
+                let timer = self.slow_handle_timer();
                 let len = self.handler.handle_normal(p);
+                self.log_slow_handle("normal", &**p, timer, len);
                 if p.is_stopped() {
                     reschedule_fsms.push((i, ReschedulePolicy::Remove));
                 } else if p.get_priority() != self.handler.get_priority() {
@@ -329,7 +395,7 @@ impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
                 }
             }
             let mut fsm_cnt = batch.normals.len();
-            while batch.normals.len() < max_batch_size {
+            while batch.normals.len() < max_batch_size && !self.over_poll_budget(round_timer) {
                 if let Ok(fsm) = self.fsm_receiver.try_recv() {
                     run = batch.push(fsm);
                 }
@@ -339,7 +405,9 @@ impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
                 if !run || fsm_cnt >= batch.normals.len() {
                     break;
                 }
+                let timer = self.slow_handle_timer();
                 let len = self.handler.handle_normal(&mut batch.normals[fsm_cnt]);
+                self.log_slow_handle("normal", &*batch.normals[fsm_cnt], timer, len);
                 if batch.normals[fsm_cnt].is_stopped() {
                     reschedule_fsms.push((fsm_cnt, ReschedulePolicy::Remove));
                 } else if let Some(l) = len {
@@ -358,6 +426,20 @@ impl<N: Fsm, C: Fsm, Handler: PollHandler<N, C>> Poller<N, C, Handler> {
                     ReschedulePolicy::Schedule => batch.reschedule(&self.router, r),
                 }
             }
+            self.busy_time
+                .inc_by(duration_to_sec(busy_timer.saturating_elapsed()));
+        }
+        // The poller may be exiting because the pool is shrunk while the
+        // rest of the system keeps running, so hand the remaining FSMs back
+        // instead of dropping them.
+        while !batch.normals.is_empty() {
+            let index = batch.normals.len() - 1;
+            batch.reschedule(&self.router, index);
+        }
+        if let Some(fsm) = batch.control.take() {
+            self.router
+                .control_box
+                .release_and_notify(fsm, &self.router.control_scheduler);
         }
         batch.clear();
     }
@@ -384,7 +466,10 @@ pub struct BatchSystem<N: Fsm, C: Fsm> {
     max_batch_size: usize,
     workers: Vec<JoinHandle<()>>,
     reschedule_duration: Duration,
+    slow_handle_threshold: Duration,
+    poll_budget: Duration,
     low_priority_pool_size: usize,
+    shutdown_hooks: Vec<Box<dyn FnOnce() + Send>>,
 }
 
 impl<N, C> BatchSystem<N, C>
@@ -396,7 +481,7 @@ where
         &self.router
     }
 
-    fn start_poller<B>(&mut self, name: String, priority: Priority, builder: &mut B)
+    fn start_poller<B>(&mut self, name: String, pool_name: &str, priority: Priority, builder: &mut B)
     where
         B: HandlerBuilder<N, C>,
         B::Handler: Send + 'static,
@@ -412,6 +497,10 @@ where
             handler,
             max_batch_size: self.max_batch_size,
             reschedule_duration: self.reschedule_duration,
+            slow_handle_threshold: self.slow_handle_threshold,
+            poll_budget: self.poll_budget,
+            busy_time: POLLER_BUSY_SECONDS.with_label_values(&[pool_name]),
+            idle_time: POLLER_IDLE_SECONDS.with_label_values(&[pool_name]),
         };
         let props = tikv_util::thread_group::current_properties();
         let t = thread::Builder::new()
@@ -434,6 +523,7 @@ where
         for i in 0..self.pool_size {
             self.start_poller(
                 thd_name!(format!("{}-{}", name_prefix, i)),
+                &name_prefix,
                 Priority::Normal,
                 &mut builder,
             );
@@ -441,6 +531,7 @@ where
         for i in 0..self.low_priority_pool_size {
             self.start_poller(
                 thd_name!(format!("{}-low-{}", name_prefix, i)),
+                &name_prefix,
                 Priority::Low,
                 &mut builder,
             );
@@ -448,6 +539,63 @@ where
         self.name_prefix = Some(name_prefix);
     }
 
+    /// The current size of the normal priority poller pool.
+    pub fn pool_size(&self) -> usize {
+        self.pool_size
+    }
+
+    /// Resize the normal priority poller pool to `new_size` threads.
+    ///
+    /// Growing spawns new pollers with handlers built by `builder`. Shrinking
+    /// sends exit signals to the pool; each signal stops one poller after it
+    /// finishes its current round, and the FSMs it holds are handed back to
+    /// the scheduler, so no message is lost.
+    pub fn resize_pool<B>(&mut self, new_size: usize, builder: &mut B) -> Result<(), String>
+    where
+        B: HandlerBuilder<N, C>,
+        B::Handler: Send + 'static,
+    {
+        let name_prefix = match self.name_prefix {
+            Some(ref p) => p.clone(),
+            None => return Err("batch system is not spawned".to_owned()),
+        };
+        if new_size == 0 {
+            return Err("pool size must be greater than 0".to_owned());
+        }
+        if new_size > self.pool_size {
+            for _ in self.pool_size..new_size {
+                // `workers.len()` keeps growing across resizes, so thread
+                // names stay unique.
+                self.start_poller(
+                    thd_name!(format!("{}-{}", name_prefix, self.workers.len())),
+                    &name_prefix,
+                    Priority::Normal,
+                    builder,
+                );
+            }
+        } else {
+            for _ in new_size..self.pool_size {
+                let _ = self.router.normal_scheduler.sender.send(FsmTypes::Empty);
+            }
+        }
+        info!(
+            "resize poller pool of batch system {} from {} to {}",
+            name_prefix, self.pool_size, new_size
+        );
+        self.pool_size = new_size;
+        Ok(())
+    }
+
+    /// Registers a hook to be invoked during `shutdown`.
+    ///
+    /// Hooks fire after all poller threads have been joined but before the
+    /// system releases its resources, so they are a deterministic place to
+    /// flush state that teardown relies on. They are invoked in registration
+    /// order. Hooks registered after `shutdown` has run will never fire.
+    pub fn register_shutdown_hook(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.shutdown_hooks.push(hook);
+    }
+
     /// Shutdown the batch system and wait till all background threads exit.
     pub fn shutdown(&mut self) {
         if self.name_prefix.is_none() {
@@ -467,6 +615,10 @@ where
         if let Some(e) = last_error {
             safe_panic!("failed to join worker thread: {:?}", e);
         }
+        // All pollers have stopped, run hooks before resources are released.
+        for hook in self.shutdown_hooks.drain(..) {
+            hook();
+        }
         info!("batch system {} is stopped.", name_prefix);
     }
 }
@@ -502,8 +654,11 @@ pub fn create_system<N: Fsm, C: Fsm>(
         pool_size: cfg.pool_size,
         max_batch_size: cfg.max_batch_size(),
         reschedule_duration: cfg.reschedule_duration.0,
+        slow_handle_threshold: cfg.slow_handle_threshold.0,
+        poll_budget: cfg.poll_budget.0,
         workers: vec![],
         low_priority_pool_size: cfg.low_priority_pool_size,
+        shutdown_hooks: vec![],
     };
     (router, system)
 }
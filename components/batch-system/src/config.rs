@@ -1,5 +1,6 @@
 // Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
 
+use online_config::{ConfigChange, ConfigManager};
 use serde::{Deserialize, Serialize};
 use tikv_util::config::ReadableDuration;
 
@@ -11,6 +12,15 @@ pub struct Config {
     pub pool_size: usize,
     pub reschedule_duration: ReadableDuration,
     pub low_priority_pool_size: usize,
+    /// Emit a warn log when handling a single FSM takes longer than this.
+    /// 0 (the default) disables the log.
+    pub slow_handle_threshold: ReadableDuration,
+    /// Maximum duration a poller spends on a single round before it
+    /// unconditionally hands unhandled FSMs back to the scheduler. A small
+    /// budget improves latency fairness between FSMs under extreme load at
+    /// the cost of some throughput spent on rescheduling. 0 (the default)
+    /// keeps rounds unbounded, the historical behavior.
+    pub poll_budget: ReadableDuration,
 }
 
 impl Config {
@@ -27,6 +37,36 @@ impl Default for Config {
             pool_size: 2,
             reschedule_duration: ReadableDuration::secs(5),
             low_priority_pool_size: 1,
+            slow_handle_threshold: ReadableDuration::secs(0),
+            poll_budget: ReadableDuration::secs(0),
         }
     }
 }
+
+/// Applies online changes of `pool_size` to a running batch system.
+///
+/// Resizing needs the `BatchSystem` and its `HandlerBuilder`, which stay with
+/// the owner of the system, so the owner registers a callback that performs
+/// the actual `resize_pool` call. Invalid sizes are rejected by `resize_pool`
+/// before anything is applied.
+pub struct BatchSystemConfigManager {
+    resize_pool: Box<dyn Fn(usize) -> Result<(), String> + Send + Sync>,
+}
+
+impl BatchSystemConfigManager {
+    pub fn new(
+        resize_pool: Box<dyn Fn(usize) -> Result<(), String> + Send + Sync>,
+    ) -> BatchSystemConfigManager {
+        BatchSystemConfigManager { resize_pool }
+    }
+}
+
+impl ConfigManager for BatchSystemConfigManager {
+    fn dispatch(&mut self, mut change: ConfigChange) -> online_config::Result<()> {
+        if let Some(v) = change.remove("pool_size") {
+            let size: usize = v.into();
+            (self.resize_pool)(size)?;
+        }
+        Ok(())
+    }
+}
@@ -3,8 +3,9 @@
 use crate::mailbox::BasicMailbox;
 use std::borrow::Cow;
 use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::{ptr, usize};
+use tikv_util::memory::HeapSize;
 
 // The FSM is notified.
 const NOTIFYSTATE_NOTIFIED: usize = 0;
@@ -33,7 +34,7 @@ pub trait FsmScheduler {
 /// A Fsm is a finite state machine. It should be able to be notified for
 /// updating internal state according to incoming messages.
 pub trait Fsm {
-    type Message: Send;
+    type Message: Send + HeapSize;
 
     fn is_stopped(&self) -> bool;
 
@@ -61,6 +62,8 @@ pub struct FsmState<N> {
     status: AtomicUsize,
     data: AtomicPtr<N>,
     state_cnt: Arc<AtomicUsize>,
+    // Invoked once the owned FSM is finally dropped. See `set_on_drop`.
+    on_drop: Mutex<Option<Box<dyn FnOnce() + Send>>>,
 }
 
 impl<N: Fsm> FsmState<N> {
@@ -70,6 +73,21 @@ impl<N: Fsm> FsmState<N> {
             status: AtomicUsize::new(NOTIFYSTATE_IDLE),
             data: AtomicPtr::new(Box::into_raw(data)),
             state_cnt,
+            on_drop: Mutex::new(None),
+        }
+    }
+
+    /// Set a callback that fires when the owned FSM is dropped, which is
+    /// the last step of closing a mailbox. If the FSM was already dropped
+    /// before the callback is set, it fires when the state itself is dropped.
+    pub(crate) fn set_on_drop(&self, cb: Box<dyn FnOnce() + Send>) {
+        *self.on_drop.lock().unwrap() = Some(cb);
+    }
+
+    #[inline]
+    fn fire_on_drop(&self) {
+        if let Some(cb) = self.on_drop.lock().unwrap().take() {
+            cb();
         }
     }
 
@@ -130,6 +148,7 @@ impl<N: Fsm> FsmState<N> {
                 Err(NOTIFYSTATE_DROP) => {
                     let ptr = self.data.swap(ptr::null_mut(), Ordering::AcqRel);
                     unsafe { Box::from_raw(ptr) };
+                    self.fire_on_drop();
                     return;
                 }
                 Err(s) => s,
@@ -151,6 +170,7 @@ impl<N: Fsm> FsmState<N> {
             unsafe {
                 Box::from_raw(ptr);
             }
+            self.fire_on_drop();
         }
     }
 }
@@ -161,6 +181,9 @@ impl<N> Drop for FsmState<N> {
         if !ptr.is_null() {
             unsafe { Box::from_raw(ptr) };
         }
+        if let Some(cb) = self.on_drop.lock().unwrap().take() {
+            cb();
+        }
         self.state_cnt.fetch_sub(1, Ordering::Relaxed);
     }
 }
@@ -4,13 +4,15 @@ mod batch;
 mod config;
 mod fsm;
 mod mailbox;
+pub mod metrics;
 mod router;
 
 #[cfg(feature = "test-runner")]
 pub mod test_runner;
 
 pub use self::batch::{create_system, BatchRouter, BatchSystem, HandlerBuilder, PollHandler};
-pub use self::config::Config;
+pub use self::config::{BatchSystemConfigManager, Config};
 pub use self::fsm::{Fsm, Priority};
-pub use self::mailbox::{BasicMailbox, Mailbox};
+pub use self::mailbox::{message_dequeued, BasicMailbox, Mailbox};
+pub use tikv_util::memory::HeapSize;
 pub use self::router::Router;
@@ -1,12 +1,22 @@
 // Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
 
 use crate::fsm::{Fsm, FsmScheduler, FsmState};
+use crate::metrics::{MAILBOX_PEAK_LEN_GAUGE, PENDING_MSG_BYTES_GAUGE};
 use crossbeam::channel::{SendError, TrySendError};
 use std::borrow::Cow;
-use std::sync::atomic::AtomicUsize;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use tikv_util::memory::HeapSize;
 use tikv_util::mpsc;
 
+/// Estimated total memory of a queued message: the message struct itself
+/// plus whatever heap payload it reports owning.
+#[inline]
+fn msg_size<M: HeapSize>(msg: &M) -> i64 {
+    (mem::size_of::<M>() + msg.heap_size()) as i64
+}
+
 /// A basic mailbox.
 ///
 /// Every mailbox should have one and only one owner, who will receive all
@@ -18,6 +28,7 @@ use tikv_util::mpsc;
 pub struct BasicMailbox<Owner: Fsm> {
     sender: mpsc::LooseBoundedSender<Owner::Message>,
     state: Arc<FsmState<Owner>>,
+    peak_len: Arc<AtomicUsize>,
 }
 
 impl<Owner: Fsm> BasicMailbox<Owner> {
@@ -30,9 +41,34 @@ impl<Owner: Fsm> BasicMailbox<Owner> {
         BasicMailbox {
             sender,
             state: Arc::new(FsmState::new(fsm, state_cnt)),
+            peak_len: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Records the queue length after an enqueue into the high-watermark
+    /// and the store-wide peak gauge.
+    #[inline]
+    fn record_len(&self) {
+        let len = self.sender.len();
+        let prev = self.peak_len.fetch_max(len, Ordering::Relaxed);
+        if len > prev && len as i64 > MAILBOX_PEAK_LEN_GAUGE.get() {
+            MAILBOX_PEAK_LEN_GAUGE.set(len as i64);
         }
     }
 
+    /// The peak queue length reached since creation or the last
+    /// `reset_peak_len`. It's retained when the queue drains, so it's
+    /// useful for sizing mailbox capacity.
+    #[inline]
+    pub fn peak_len(&self) -> usize {
+        self.peak_len.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn reset_peak_len(&self) {
+        self.peak_len.store(0, Ordering::Relaxed);
+    }
+
     pub(crate) fn is_connected(&self) -> bool {
         self.sender.is_sender_connected()
     }
@@ -62,7 +98,10 @@ impl<Owner: Fsm> BasicMailbox<Owner> {
         msg: Owner::Message,
         scheduler: &S,
     ) -> Result<(), SendError<Owner::Message>> {
+        let size = msg_size(&msg);
         self.sender.force_send(msg)?;
+        PENDING_MSG_BYTES_GAUGE.add(size);
+        self.record_len();
         self.state.notify(scheduler, Cow::Borrowed(self));
         Ok(())
     }
@@ -76,7 +115,10 @@ impl<Owner: Fsm> BasicMailbox<Owner> {
         msg: Owner::Message,
         scheduler: &S,
     ) -> Result<(), TrySendError<Owner::Message>> {
+        let size = msg_size(&msg);
         self.sender.try_send(msg)?;
+        PENDING_MSG_BYTES_GAUGE.add(size);
+        self.record_len();
         self.state.notify(scheduler, Cow::Borrowed(self));
         Ok(())
     }
@@ -87,6 +129,26 @@ impl<Owner: Fsm> BasicMailbox<Owner> {
         self.sender.close_sender();
         self.state.clear();
     }
+
+    /// Like `close`, but invokes `cb` once the owned FSM is fully dropped.
+    #[inline]
+    pub(crate) fn close_with_callback(&self, cb: Box<dyn FnOnce() + Send>) {
+        self.state.set_on_drop(cb);
+        self.close();
+    }
+
+    /// Puts the owner back and wakes it up if messages were queued while it
+    /// was taken out. Used by `Router::attach` to resume a detached FSM.
+    pub(crate) fn release_and_notify<S: FsmScheduler<Fsm = Owner>>(
+        &self,
+        fsm: Box<Owner>,
+        scheduler: &S,
+    ) {
+        self.state.release(fsm);
+        if !self.is_empty() {
+            self.state.notify(scheduler, Cow::Borrowed(self));
+        }
+    }
 }
 
 impl<Owner: Fsm> Clone for BasicMailbox<Owner> {
@@ -95,10 +157,20 @@ impl<Owner: Fsm> Clone for BasicMailbox<Owner> {
         BasicMailbox {
             sender: self.sender.clone(),
             state: self.state.clone(),
+            peak_len: self.peak_len.clone(),
         }
     }
 }
 
+/// Subtracts a dequeued message from the pending-memory gauge.
+///
+/// Mailbox receivers live with the FSM owners outside the batch system, so
+/// owners call this when draining their queues. Messages dropped with a
+/// closed mailbox aren't subtracted, which keeps the gauge approximate.
+pub fn message_dequeued<M: HeapSize>(msg: &M) {
+    PENDING_MSG_BYTES_GAUGE.sub(msg_size(msg));
+}
+
 /// A more high level mailbox.
 pub struct Mailbox<Owner, Scheduler>
 where
@@ -0,0 +1,35 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+use lazy_static::lazy_static;
+use prometheus::*;
+
+lazy_static! {
+    pub static ref FSM_RESCHEDULE_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "tikv_batch_system_fsm_reschedule_total",
+        "Total number of FSMs requeued to the scheduler before their mailboxes were drained.",
+        &["type"]
+    )
+    .unwrap();
+    pub static ref PENDING_MSG_BYTES_GAUGE: IntGauge = register_int_gauge!(
+        "tikv_batch_system_pending_message_bytes",
+        "Approximate memory held by messages queued in all mailboxes."
+    )
+    .unwrap();
+    pub static ref MAILBOX_PEAK_LEN_GAUGE: IntGauge = register_int_gauge!(
+        "tikv_batch_system_mailbox_peak_len",
+        "Highest queue depth any mailbox reached, updated on enqueue."
+    )
+    .unwrap();
+    pub static ref POLLER_BUSY_SECONDS: CounterVec = register_counter_vec!(
+        "tikv_batch_system_poller_busy_seconds_total",
+        "Cumulative seconds pollers of the pool spent handling ready FSMs.",
+        &["name"]
+    )
+    .unwrap();
+    pub static ref POLLER_IDLE_SECONDS: CounterVec = register_counter_vec!(
+        "tikv_batch_system_poller_idle_seconds_total",
+        "Cumulative seconds pollers of the pool spent blocked waiting for ready FSMs.",
+        &["name"]
+    )
+    .unwrap();
+}
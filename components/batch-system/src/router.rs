@@ -50,7 +50,7 @@ pub struct Router<N: Fsm, C: Fsm, Ns, Cs> {
     // it's not possible to write FsmScheduler<Fsm=C> + FsmScheduler<Fsm=N>
     // for now.
     pub(crate) normal_scheduler: Ns,
-    control_scheduler: Cs,
+    pub(crate) control_scheduler: Cs,
 
     // Count of Mailboxes that is not destroyed.
     // Added when a Mailbox created, and subtracted it when a Mailbox destroyed.
@@ -162,6 +162,37 @@ where
             .store(normals.map.len(), Ordering::Relaxed);
     }
 
+    /// Sends to `addr`, atomically registering the mailbox built by `create`
+    /// first if none is registered. Checking registration and sending as two
+    /// steps races with concurrent creators; holding the registry lock across
+    /// both guarantees exactly one FSM is created and no message is lost in
+    /// between. A previously closed mailbox is replaced like in `register`.
+    pub fn send_or_create<F>(
+        &self,
+        addr: u64,
+        msg: N::Message,
+        create: F,
+    ) -> Result<(), TrySendError<N::Message>>
+    where
+        F: FnOnce() -> BasicMailbox<N>,
+    {
+        let mut normals = self.normals.lock().unwrap();
+        if let Some(mailbox) = normals.map.get(&addr) {
+            if mailbox.is_connected() {
+                return mailbox.try_send(msg, &self.normal_scheduler);
+            }
+        }
+        let mailbox = create();
+        let res = mailbox.try_send(msg, &self.normal_scheduler);
+        if let Some(old) = normals.map.insert(addr, mailbox) {
+            old.close();
+        }
+        normals
+            .alive_cnt
+            .store(normals.map.len(), Ordering::Relaxed);
+        res
+    }
+
     pub fn register_all(&self, mailboxes: Vec<(u64, BasicMailbox<N>)>) {
         let mut normals = self.normals.lock().unwrap();
         normals.map.reserve(mailboxes.len());
@@ -280,6 +311,33 @@ where
         }
     }
 
+    /// Returns the peak mailbox depth the FSM reached since it was
+    /// registered or since the last `reset_mailbox_peak_len`, or `None`
+    /// when the address is unknown.
+    pub fn mailbox_peak_len(&self, addr: u64) -> Option<usize> {
+        let mailboxes = self.normals.lock().unwrap();
+        mailboxes.map.get(&addr).map(|m| m.peak_len())
+    }
+
+    /// Resets the peak mailbox depth of the FSM.
+    pub fn reset_mailbox_peak_len(&self, addr: u64) {
+        let mailboxes = self.normals.lock().unwrap();
+        if let Some(m) = mailboxes.map.get(&addr) {
+            m.reset_peak_len();
+        }
+    }
+
+    /// Collect the addresses of all registered normal fsms.
+    ///
+    /// The result is a snapshot taken under the registry lock: fsms may be
+    /// registered or closed right after it returns, so it can be stale
+    /// immediately. It's cheaper than `broadcast_normal` when the caller
+    /// only needs the set of addresses and no message has to be delivered.
+    pub fn collect_addresses(&self) -> Vec<u64> {
+        let mailboxes = self.normals.lock().unwrap();
+        mailboxes.map.keys().copied().collect()
+    }
+
     /// Try to notify all fsm that the cluster is being shutdown.
     pub fn broadcast_shutdown(&self) {
         info!("broadcasting shutdown");
@@ -308,6 +366,65 @@ where
             .store(mailboxes.map.len(), Ordering::Relaxed);
     }
 
+    /// Close the mailbox of address and invoke `cb` once its FSM is fully
+    /// removed.
+    ///
+    /// The mailbox is marked closed immediately so no new message can be
+    /// delivered. If the FSM is being polled, it keeps handling the messages
+    /// already delivered to it and `cb` fires after the poller releases the
+    /// FSM for the last time. If there is no mailbox for the address, `cb`
+    /// fires immediately.
+    pub fn close_with_callback(&self, addr: u64, cb: Box<dyn FnOnce() + Send>) {
+        info!("[region {}] shutdown mailbox", addr);
+        unsafe { &mut *self.caches.as_ptr() }.remove(&addr);
+        let mut mailboxes = self.normals.lock().unwrap();
+        match mailboxes.map.remove(&addr) {
+            Some(mb) => mb.close_with_callback(cb),
+            None => {
+                drop(mailboxes);
+                cb();
+                return;
+            }
+        }
+        mailboxes
+            .alive_cnt
+            .store(mailboxes.map.len(), Ordering::Relaxed);
+    }
+
+    /// Removes the mailbox of the address and takes its FSM out so the pair
+    /// can be handed over to another batch system via `attach`.
+    ///
+    /// Returns `None` if there is no such mailbox or the FSM is currently
+    /// being polled; callers may retry the latter case. On success the FSM
+    /// can't be scheduled anymore: senders holding stale clones of the
+    /// mailbox can still enqueue messages, but they stay in the queue and
+    /// are handled only after the pair is registered again with `attach`.
+    /// The caller must make sure the source system won't touch the address
+    /// afterwards.
+    pub fn detach(&self, addr: u64) -> Option<(Box<N>, BasicMailbox<N>)> {
+        unsafe { &mut *self.caches.as_ptr() }.remove(&addr);
+        let mut mailboxes = self.normals.lock().unwrap();
+        let mailbox = match mailboxes.map.get(&addr) {
+            Some(mailbox) => mailbox.clone(),
+            None => return None,
+        };
+        // Taking the FSM marks its state as notified, so concurrent sends
+        // can no longer schedule it on this system.
+        let fsm = mailbox.take_fsm()?;
+        mailboxes.map.remove(&addr);
+        mailboxes
+            .alive_cnt
+            .store(mailboxes.map.len(), Ordering::Relaxed);
+        Some((fsm, mailbox))
+    }
+
+    /// Registers an FSM and mailbox pair taken from another router via
+    /// `detach`. Messages queued during the handoff are scheduled right away.
+    pub fn attach(&self, addr: u64, fsm: Box<N>, mailbox: BasicMailbox<N>) {
+        self.register(addr, mailbox.clone());
+        mailbox.release_and_notify(fsm, &self.normal_scheduler);
+    }
+
     pub fn clear_cache(&self) {
         unsafe { &mut *self.caches.as_ptr() }.clear();
     }
@@ -16,6 +16,8 @@ pub enum Message {
     Callback(Box<dyn FnOnce(&Handler, &mut Runner) + Send + 'static>),
 }
 
+impl HeapSize for Message {}
+
 /// A simple runner used for benchmarking only.
 pub struct Runner {
     is_stopped: bool,
@@ -84,14 +86,19 @@ impl Handler {
     fn handle(&mut self, r: &mut Runner) -> Option<usize> {
         for _ in 0..16 {
             match r.recv.try_recv() {
-                Ok(Message::Loop(count)) => {
-                    // Some calculation to represent a CPU consuming work
-                    for _ in 0..count {
-                        r.res *= count;
-                        r.res %= count + 1;
+                Ok(msg) => {
+                    message_dequeued(&msg);
+                    match msg {
+                        Message::Loop(count) => {
+                            // Some calculation to represent a CPU consuming work
+                            for _ in 0..count {
+                                r.res *= count;
+                                r.res %= count + 1;
+                            }
+                        }
+                        Message::Callback(cb) => cb(self, r),
                     }
                 }
-                Ok(Message::Callback(cb)) => cb(self, r),
                 Err(_) => break,
             }
         }
@@ -164,3 +164,237 @@ fn test_router_trace() {
     assert_eq!(router.alive_cnt().load(Ordering::Relaxed), 1024);
     assert_eq!(router.state_cnt().load(Ordering::Relaxed), 1025);
 }
+
+#[test]
+fn test_collect_addresses() {
+    let (control_tx, control_fsm) = Runner::new(10);
+    let (router, mut system) =
+        batch_system::create_system(&Config::default(), control_tx, control_fsm);
+    system.spawn("test".to_owned(), Builder::new());
+
+    assert!(router.collect_addresses().is_empty());
+    for addr in &[2, 3, 5, 7] {
+        let (sender, runner) = Runner::new(10);
+        let mailbox = BasicMailbox::new(sender, runner, router.state_cnt().clone());
+        router.register(*addr, mailbox);
+    }
+    let mut addresses = router.collect_addresses();
+    addresses.sort_unstable();
+    assert_eq!(addresses, vec![2, 3, 5, 7]);
+
+    // Closed fsms must disappear from the snapshot.
+    router.close(3);
+    let mut addresses = router.collect_addresses();
+    addresses.sort_unstable();
+    assert_eq!(addresses, vec![2, 5, 7]);
+    system.shutdown();
+}
+
+#[test]
+fn test_close_with_callback() {
+    let (control_tx, control_fsm) = Runner::new(10);
+    let (router, mut system) =
+        batch_system::create_system(&Config::default(), control_tx, control_fsm);
+    system.spawn("test".to_owned(), Builder::new());
+
+    let (tx, rx) = mpsc::unbounded();
+    let tx_ = tx.clone();
+    let r = router.clone();
+    router
+        .send_control(Message::Callback(Box::new(
+            move |_: &Handler, _: &mut Runner| {
+                let (tx, runner) = Runner::new(10);
+                r.register(1, BasicMailbox::new(tx, runner, Arc::default()));
+                tx_.send(0).unwrap();
+            },
+        )))
+        .unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(0));
+
+    // Closing a non-existent mailbox fires the callback immediately.
+    let tx_ = tx.clone();
+    router.close_with_callback(2, Box::new(move || tx_.send(100).unwrap()));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(100));
+
+    let (started_tx, started_rx) = mpsc::unbounded();
+    let tx_ = tx.clone();
+    router
+        .send(
+            1,
+            Message::Callback(Box::new(move |_: &Handler, _: &mut Runner| {
+                started_tx.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+                tx_.send(1).unwrap();
+            })),
+        )
+        .unwrap();
+    // Make sure the poller owns the FSM before closing so the queued message
+    // is still handled.
+    started_rx.recv_timeout(Duration::from_secs(3)).unwrap();
+    let tx_ = tx.clone();
+    router.close_with_callback(1, Box::new(move || tx_.send(2).unwrap()));
+    // The callback must fire after the last message is handled.
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(1));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(2));
+    system.shutdown();
+}
+
+#[test]
+fn test_detach_attach() {
+    let (control_tx1, control_fsm1) = Runner::new(10);
+    let (router1, mut system1) =
+        batch_system::create_system(&Config::default(), control_tx1, control_fsm1);
+    system1.spawn("test1".to_owned(), Builder::new());
+    let (control_tx2, control_fsm2) = Runner::new(10);
+    let (router2, mut system2) =
+        batch_system::create_system(&Config::default(), control_tx2, control_fsm2);
+    system2.spawn("test2".to_owned(), Builder::new());
+
+    // Detaching a missing address returns nothing.
+    assert!(router1.detach(1).is_none());
+
+    let (sender, runner) = Runner::new(10);
+    router1.register(1, BasicMailbox::new(sender.clone(), runner, Arc::default()));
+
+    // Queue messages through the raw sender so the FSM is not scheduled and
+    // they are still pending when the mailbox is moved.
+    let (tx, rx) = mpsc::unbounded();
+    for i in 0..2 {
+        let tx_ = tx.clone();
+        sender
+            .try_send(Message::Callback(Box::new(
+                move |_: &Handler, _: &mut Runner| {
+                    tx_.send(i).unwrap();
+                },
+            )))
+            .unwrap();
+    }
+
+    let (fsm, mailbox) = router1.detach(1).unwrap();
+    // The source no longer knows the address.
+    match router1.send(1, noop()) {
+        Err(TrySendError::Disconnected(_)) => (),
+        other => panic!("expect disconnected, got {:?}", other),
+    }
+
+    // The queued messages are drained on the target system.
+    router2.attach(1, fsm, mailbox);
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(0));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(1));
+
+    // New messages reach the FSM through the target router.
+    let tx_ = tx.clone();
+    router2
+        .send(
+            1,
+            Message::Callback(Box::new(move |_: &Handler, _: &mut Runner| {
+                tx_.send(2).unwrap();
+            })),
+        )
+        .unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(2));
+
+    system1.shutdown();
+    system2.shutdown();
+}
+
+#[test]
+fn test_mailbox_peak_len() {
+    let (control_tx, control_fsm) = Runner::new(10);
+    let (router, mut system) =
+        batch_system::create_system(&Config::default(), control_tx, control_fsm);
+    system.spawn("test".to_owned(), Builder::new());
+
+    let (sender, runner) = Runner::new(100);
+    let mailbox = BasicMailbox::new(sender, runner, router.state_cnt().clone());
+    router.register(1, mailbox);
+    assert_eq!(router.mailbox_peak_len(1), Some(0));
+    assert_eq!(router.mailbox_peak_len(2), None);
+
+    // Block the FSM so a burst piles up in its mailbox.
+    let (block_tx, block_rx) = mpsc::unbounded();
+    router
+        .send(
+            1,
+            Message::Callback(Box::new(move |_: &Handler, _: &mut Runner| {
+                block_rx.recv_timeout(Duration::from_secs(100)).unwrap();
+            })),
+        )
+        .unwrap();
+    for _ in 0..10 {
+        router.send(1, noop()).unwrap();
+    }
+    block_tx.send(1).unwrap();
+
+    // Flush so the queue is fully drained, then the peak must be retained.
+    let (tx, rx) = mpsc::unbounded();
+    router
+        .force_send(
+            1,
+            Message::Callback(Box::new(move |_: &Handler, _: &mut Runner| {
+                tx.send(1).unwrap();
+            })),
+        )
+        .unwrap();
+    rx.recv_timeout(Duration::from_secs(100)).unwrap();
+    let peak = router.mailbox_peak_len(1).unwrap();
+    assert!(peak >= 10, "peak {} should cover the burst", peak);
+
+    router.reset_mailbox_peak_len(1);
+    assert_eq!(router.mailbox_peak_len(1), Some(0));
+    system.shutdown();
+}
+
+#[test]
+fn test_send_or_create() {
+    let (control_tx, control_fsm) = Runner::new(10);
+    let (router, mut system) =
+        batch_system::create_system(&Config::default(), control_tx, control_fsm);
+    system.spawn("test".to_owned(), Builder::new());
+
+    // Many threads race to send to the same absent address. Exactly one of
+    // them must create the FSM and no message may be dropped.
+    let created = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::new(AtomicUsize::new(0));
+    let mut handles = Vec::with_capacity(4);
+    for _ in 0..4 {
+        let router = router.clone();
+        let created = created.clone();
+        let counter = counter.clone();
+        handles.push(std::thread::spawn(move || {
+            for _ in 0..100 {
+                let created = created.clone();
+                let state_cnt = router.state_cnt().clone();
+                router
+                    .send_or_create(1, counter_closure(&counter), move || {
+                        created.fetch_add(1, Ordering::SeqCst);
+                        let (sender, runner) = Runner::new(1024);
+                        BasicMailbox::new(sender, runner, state_cnt)
+                    })
+                    .unwrap();
+            }
+        }));
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(created.load(Ordering::SeqCst), 1);
+
+    // Flush so all queued messages have been handled.
+    let (tx, rx) = mpsc::unbounded();
+    router
+        .force_send(
+            1,
+            Message::Callback(Box::new(move |_: &Handler, _: &mut Runner| {
+                tx.send(1).unwrap();
+            })),
+        )
+        .unwrap();
+    rx.recv_timeout(Duration::from_secs(100)).unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), 400);
+
+    // An existing mailbox must be reused without invoking `create`.
+    router.send_or_create(1, noop(), || unreachable!()).unwrap();
+    assert_eq!(created.load(Ordering::SeqCst), 1);
+    system.shutdown();
+}
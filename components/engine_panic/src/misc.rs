@@ -81,6 +81,10 @@ impl MiscExt for PanicEngine {
         panic!()
     }
 
+    fn get_cf_blob_gc_pending_bytes(&self, cf: &str) -> Result<Option<u64>> {
+        panic!()
+    }
+
     fn is_stalled_or_stopped(&self) -> bool {
         panic!()
     }
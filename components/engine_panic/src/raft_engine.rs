@@ -15,6 +15,14 @@ impl RaftEngineReadOnly for PanicEngine {
         panic!()
     }
 
+    fn first_index(&self, raft_group_id: u64) -> Result<Option<u64>> {
+        panic!()
+    }
+
+    fn region_log_span(&self, raft_group_id: u64) -> Result<Option<(u64, u64)>> {
+        panic!()
+    }
+
     fn fetch_entries_to(
         &self,
         region_id: u64,
@@ -0,0 +1,53 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+use criterion::*;
+use engine_rocks::util::new_default_engine;
+use engine_traits::{RaftEngine, RaftLogBatch};
+use raft::eraftpb::Entry;
+use tempfile::Builder;
+
+const ENTRY_COUNT: usize = 5000;
+const ENTRY_DATA_LEN: usize = 256;
+
+fn make_entries() -> Vec<Entry> {
+    (1..=ENTRY_COUNT as u64)
+        .map(|index| {
+            let mut e = Entry::default();
+            e.set_index(index);
+            e.set_data(vec![0; ENTRY_DATA_LEN].into());
+            e
+        })
+        .collect()
+}
+
+// Compares building a 5000-entry raft log batch with and without the
+// `reserve` hint. Reserving sizes the write batch buffer up front, so the
+// appends don't reallocate it while the batch grows.
+fn bench_raft_log_batch_append(c: &mut Criterion) {
+    let dir = Builder::new()
+        .prefix("bench_raft_log_batch_append")
+        .tempdir()
+        .unwrap();
+    let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+    let entries = make_entries();
+    let bytes = ENTRY_COUNT * ENTRY_DATA_LEN;
+
+    c.bench_function("raft_log_batch::append", |b| {
+        b.iter(|| {
+            let mut batch = engine.log_batch(0);
+            batch.append(1, entries.clone()).unwrap();
+            black_box(batch);
+        })
+    });
+    c.bench_function("raft_log_batch::append_reserved", |b| {
+        b.iter(|| {
+            let mut batch = engine.log_batch(0);
+            batch.reserve(ENTRY_COUNT, bytes);
+            batch.append(1, entries.clone()).unwrap();
+            black_box(batch);
+        })
+    });
+}
+
+criterion_group!(benches, bench_raft_log_batch_append);
+criterion_main!(benches);
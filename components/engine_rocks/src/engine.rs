@@ -1,9 +1,10 @@
 // Copyright 2019 TiKV Project Authors. Licensed under Apache-2.0.
 
 use std::any::Any;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use engine_traits::{
     Error, IterOptions, Iterable, KvEngine, Peekable, ReadOptions, Result, SyncMutable,
@@ -26,6 +27,9 @@ use crate::{RocksEngineIterator, RocksSnapshot};
 pub struct RocksEngine {
     db: Arc<DB>,
     shared_block_cache: bool,
+    raft_log_checksum: bool,
+    gc_flush_batch_keys: usize,
+    max_retained_entries: Arc<Mutex<HashMap<u64, u64>>>,
 }
 
 impl RocksEngine {
@@ -33,6 +37,9 @@ impl RocksEngine {
         RocksEngine {
             db,
             shared_block_cache: false,
+            raft_log_checksum: false,
+            gc_flush_batch_keys: <Self as engine_traits::WriteBatchExt>::WRITE_BATCH_MAX_KEYS,
+            max_retained_entries: Arc::default(),
         }
     }
 
@@ -63,6 +70,48 @@ impl RocksEngine {
     pub fn set_shared_block_cache(&mut self, enable: bool) {
         self.shared_block_cache = enable;
     }
+
+    /// Enables appending a CRC32 to every raft log entry on write and
+    /// verifying it on read. Must be set before the engine is used as a raft
+    /// engine and kept consistent with what the data was written with.
+    pub fn set_raft_log_checksum(&mut self, enable: bool) {
+        self.raft_log_checksum = enable;
+    }
+
+    pub fn raft_log_checksum_enabled(&self) -> bool {
+        self.raft_log_checksum
+    }
+
+    /// Sets how many pending deletes the raft log gc accumulates before
+    /// flushing its write batch, see `RaftEngine::gc`. Lowering it bounds
+    /// the size of individual gc writes on slow disks.
+    pub fn set_gc_flush_batch_keys(&mut self, keys: usize) {
+        assert!(keys > 0);
+        self.gc_flush_batch_keys = keys;
+    }
+
+    pub fn gc_flush_batch_keys(&self) -> usize {
+        self.gc_flush_batch_keys
+    }
+
+    /// Caps the raft log entries retained for the raft group, see
+    /// `RaftEngine::set_max_retained_entries`. 0 removes the cap.
+    pub fn set_max_retained_entries(&self, raft_group_id: u64, max: u64) {
+        let mut caps = self.max_retained_entries.lock().unwrap();
+        if max == 0 {
+            caps.remove(&raft_group_id);
+        } else {
+            caps.insert(raft_group_id, max);
+        }
+    }
+
+    pub fn max_retained_entries(&self, raft_group_id: u64) -> Option<u64> {
+        self.max_retained_entries
+            .lock()
+            .unwrap()
+            .get(&raft_group_id)
+            .copied()
+    }
 }
 
 impl KvEngine for RocksEngine {
@@ -341,6 +341,14 @@ impl MiscExt for RocksEngine {
         ))
     }
 
+    fn get_cf_blob_gc_pending_bytes(&self, cf: &str) -> Result<Option<u64>> {
+        let handle = util::get_cf_handle(self.as_inner(), cf)?;
+        Ok(crate::util::get_cf_blob_gc_pending_bytes(
+            self.as_inner(),
+            &handle,
+        ))
+    }
+
     fn is_stalled_or_stopped(&self) -> bool {
         const ROCKSDB_IS_WRITE_STALLED: &str = "rocksdb.is-write-stalled";
         const ROCKSDB_IS_WRITE_STOPPED: &str = "rocksdb.is-write-stopped";
@@ -3,16 +3,67 @@
 use crate::{util, RocksEngine, RocksWriteBatch};
 
 use engine_traits::{
-    Error, Iterable, KvEngine, MiscExt, Mutable, Peekable, RaftEngine, RaftEngineReadOnly,
-    RaftLogBatch, Result, SyncMutable, WriteBatch, WriteBatchExt, WriteOptions, CF_DEFAULT,
+    CompactExt, Error, Iterable, KvEngine, MiscExt, Mutable, Peekable, RaftEngine,
+    RaftEngineDebug, RaftEngineReadOnly, RaftLogBatch, Result, SyncMutable, WalStats, WriteBatch,
+    WriteBatchExt, WriteOptions, CF_DEFAULT,
 };
-use kvproto::raft_serverpb::RaftLocalState;
+use kvproto::raft_serverpb::{RaftApplyState, RaftLocalState};
 use protobuf::Message;
 use raft::eraftpb::Entry;
+use tikv_util::time::{duration_to_sec, Instant};
 use tikv_util::{box_err, box_try};
 
 const RAFT_LOG_MULTI_GET_CNT: u64 = 8;
 
+lazy_static::lazy_static! {
+    static ref RAFT_LOG_FETCH_DURATION_HISTOGRAM: prometheus::HistogramVec =
+        prometheus::register_histogram_vec!(
+            "tikv_engine_raft_log_fetch_duration_seconds",
+            "Bucketed histogram of raft log fetch duration, by read path.",
+            &["path"],
+            prometheus::exponential_buckets(0.00005, 2.0, 20).unwrap()
+        )
+        .unwrap();
+    static ref RAFT_LOG_REWRITE_RECLAIMED_BYTES: prometheus::IntCounter =
+        prometheus::register_int_counter!(
+            "tikv_engine_raft_log_rewrite_reclaimed_bytes_total",
+            "Total bytes reclaimed by targeted raft log rewrites."
+        )
+        .unwrap();
+}
+
+/// Number of trailing bytes occupied by the CRC32 when entry checksums are
+/// enabled.
+const ENTRY_CHECKSUM_LEN: usize = 4;
+
+/// Decodes a raft log value written by `append_impl`.
+///
+/// With checksums enabled the value is the protobuf-serialized entry followed
+/// by a little-endian CRC32 (IEEE) of the serialized bytes; without them it's
+/// the bare serialized entry, which is also the historical on-disk format.
+/// The flag must match what the value was written with.
+fn decode_entry(value: &[u8], verify_checksum: bool) -> Result<Entry> {
+    let body = if verify_checksum {
+        if value.len() < ENTRY_CHECKSUM_LEN {
+            return Err(Error::ChecksumMismatch);
+        }
+        let (body, tail) = value.split_at(value.len() - ENTRY_CHECKSUM_LEN);
+        let mut stored = [0; ENTRY_CHECKSUM_LEN];
+        stored.copy_from_slice(tail);
+        let mut digest = crc32fast::Hasher::new();
+        digest.update(body);
+        if digest.finalize() != u32::from_le_bytes(stored) {
+            return Err(Error::ChecksumMismatch);
+        }
+        body
+    } else {
+        value
+    };
+    let mut entry = Entry::default();
+    entry.merge_from_bytes(body)?;
+    Ok(entry)
+}
+
 impl RaftEngineReadOnly for RocksEngine {
     fn get_raft_state(&self, raft_group_id: u64) -> Result<Option<RaftLocalState>> {
         let key = keys::raft_state_key(raft_group_id);
@@ -21,7 +72,33 @@ impl RaftEngineReadOnly for RocksEngine {
 
     fn get_entry(&self, raft_group_id: u64, index: u64) -> Result<Option<Entry>> {
         let key = keys::raft_log_key(raft_group_id, index);
-        self.get_msg_cf(CF_DEFAULT, &key)
+        match self.get_value(&key)? {
+            Some(v) => Ok(Some(decode_entry(&v, self.raft_log_checksum_enabled())?)),
+            None => Ok(None),
+        }
+    }
+
+    fn first_index(&self, raft_group_id: u64) -> Result<Option<u64>> {
+        let seek_key = keys::raft_log_key(raft_group_id, 0);
+        let prefix = keys::raft_log_prefix(raft_group_id);
+        match self.seek(&seek_key)? {
+            Some((key, _)) if key.starts_with(&prefix) => {
+                Ok(Some(box_try!(keys::raft_log_index(&key))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn region_log_span(&self, raft_group_id: u64) -> Result<Option<(u64, u64)>> {
+        let first = match self.first_index(raft_group_id)? {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        let last = match self.get_raft_state(raft_group_id)? {
+            Some(state) => state.get_last_index(),
+            None => return Ok(None),
+        };
+        Ok(Some((first, last)))
     }
 
     fn fetch_entries_to(
@@ -32,6 +109,7 @@ impl RaftEngineReadOnly for RocksEngine {
         max_size: Option<usize>,
         buf: &mut Vec<Entry>,
     ) -> Result<usize> {
+        let timer = Instant::now();
         let (max_size, mut total_size, mut count) = (max_size.unwrap_or(usize::MAX), 0, 0);
 
         if high - low <= RAFT_LOG_MULTI_GET_CNT {
@@ -44,8 +122,7 @@ impl RaftEngineReadOnly for RocksEngine {
                 match self.get_value(&key) {
                     Ok(None) => return Err(Error::EntriesCompacted),
                     Ok(Some(v)) => {
-                        let mut entry = Entry::default();
-                        entry.merge_from_bytes(&v)?;
+                        let entry = decode_entry(&v, self.raft_log_checksum_enabled())?;
                         assert_eq!(entry.get_index(), i);
                         buf.push(entry);
                         total_size += v.len();
@@ -54,10 +131,14 @@ impl RaftEngineReadOnly for RocksEngine {
                     Err(e) => return Err(box_err!(e)),
                 }
             }
+            RAFT_LOG_FETCH_DURATION_HISTOGRAM
+                .with_label_values(&["multi_get"])
+                .observe(duration_to_sec(timer.saturating_elapsed()));
             return Ok(count);
         }
 
         let (mut check_compacted, mut next_index) = (true, low);
+        let verify_checksum = self.raft_log_checksum_enabled();
         let start_key = keys::raft_log_key(region_id, low);
         let end_key = keys::raft_log_key(region_id, high);
         self.scan(
@@ -65,8 +146,7 @@ impl RaftEngineReadOnly for RocksEngine {
             &end_key,
             true, // fill_cache
             |_, value| {
-                let mut entry = Entry::default();
-                entry.merge_from_bytes(value)?;
+                let entry = decode_entry(value, verify_checksum)?;
 
                 if check_compacted {
                     if entry.get_index() != low {
@@ -86,6 +166,10 @@ impl RaftEngineReadOnly for RocksEngine {
             },
         )?;
 
+        RAFT_LOG_FETCH_DURATION_HISTOGRAM
+            .with_label_values(&["scan"])
+            .observe(duration_to_sec(timer.saturating_elapsed()));
+
         // If we get the correct number of entries, returns.
         // Or the total size almost exceeds max_size, returns.
         if count == (high - low) as usize || total_size >= max_size {
@@ -95,6 +179,43 @@ impl RaftEngineReadOnly for RocksEngine {
         // Here means we don't fetch enough entries.
         Err(Error::EntriesUnavailable)
     }
+
+    fn for_each_entry<F>(&self, region_id: u64, low: u64, high: u64, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Entry) -> Result<bool>,
+    {
+        let verify_checksum = self.raft_log_checksum_enabled();
+        let (mut stopped, mut next_index) = (false, low);
+        let start_key = keys::raft_log_key(region_id, low);
+        let end_key = keys::raft_log_key(region_id, high);
+        self.scan(
+            &start_key,
+            &end_key,
+            true, // fill_cache
+            |_, value| {
+                let entry = decode_entry(value, verify_checksum)?;
+                if entry.get_index() != next_index {
+                    // May meet gap or has been compacted.
+                    return Ok(false);
+                }
+                next_index += 1;
+                if !f(&entry)? {
+                    stopped = true;
+                    return Ok(false);
+                }
+                Ok(true)
+            },
+        )?;
+
+        if stopped || next_index == high {
+            return Ok(());
+        }
+        if next_index == low {
+            Err(Error::EntriesCompacted)
+        } else {
+            Err(Error::EntriesUnavailable)
+        }
+    }
 }
 
 // FIXME: RaftEngine should probably be implemented generically
@@ -104,7 +225,9 @@ impl RaftEngine for RocksEngine {
     type LogBatch = RocksWriteBatch;
 
     fn log_batch(&self, capacity: usize) -> Self::LogBatch {
-        RocksWriteBatch::with_capacity(self.as_inner().clone(), capacity)
+        let mut wb = RocksWriteBatch::with_capacity(self.as_inner().clone(), capacity);
+        wb.set_raft_log_checksum(self.raft_log_checksum_enabled());
+        wb
     }
 
     fn sync(&self) -> Result<()> {
@@ -129,7 +252,7 @@ impl RaftEngine for RocksEngine {
     ) -> Result<usize> {
         let data_size = self.consume(batch, sync_log)?;
         if data_size > max_capacity {
-            *batch = self.write_batch_with_cap(shrink_to);
+            *batch = self.log_batch(shrink_to);
         }
         Ok(data_size)
     }
@@ -161,9 +284,9 @@ impl RaftEngine for RocksEngine {
     }
 
     fn append(&self, raft_group_id: u64, entries: Vec<Entry>) -> Result<usize> {
-        let mut wb = RocksWriteBatch::new(self.as_inner().clone());
-        let buf = Vec::with_capacity(1024);
-        wb.append_impl(raft_group_id, &entries, buf)?;
+        let mut wb = self.log_batch(0);
+        let mut buf = Vec::with_capacity(1024);
+        wb.append_impl(raft_group_id, &entries, &mut buf)?;
         self.consume(&mut wb, false)
     }
 
@@ -171,7 +294,18 @@ impl RaftEngine for RocksEngine {
         self.put_msg(&keys::raft_state_key(raft_group_id), state)
     }
 
-    fn gc(&self, raft_group_id: u64, mut from: u64, to: u64) -> Result<usize> {
+    fn gc(&self, raft_group_id: u64, mut from: u64, mut to: u64) -> Result<usize> {
+        if let Some(max) = self.max_retained_entries(raft_group_id) {
+            if let Some(state) = self.get_raft_state(raft_group_id)? {
+                // Trim further so at most `max` entries up to the last
+                // stored index survive this call, clamped to the caller's
+                // bound: entries at `to` and above may not be applied or
+                // replicated yet, and deleting them would leave holes
+                // behind `truncated_state`.
+                let capped = std::cmp::min((state.get_last_index() + 1).saturating_sub(max), to);
+                to = std::cmp::max(to, capped);
+            }
+        }
         if from >= to {
             return Ok(0);
         }
@@ -185,11 +319,13 @@ impl RaftEngine for RocksEngine {
             }
         }
 
+        let flush_batch_keys = self.gc_flush_batch_keys();
         let mut raft_wb = self.write_batch_with_cap(4 * 1024);
         for idx in from..to {
             let key = keys::raft_log_key(raft_group_id, idx);
             raft_wb.delete(&key)?;
-            if raft_wb.count() >= Self::WRITE_BATCH_MAX_KEYS {
+            if raft_wb.count() >= flush_batch_keys {
+                fail::fail_point!("raft_engine_gc_flush");
                 raft_wb.write()?;
                 raft_wb.clear();
             }
@@ -197,11 +333,16 @@ impl RaftEngine for RocksEngine {
 
         // TODO: disable WAL here.
         if !WriteBatch::is_empty(&raft_wb) {
+            fail::fail_point!("raft_engine_gc_flush");
             raft_wb.write()?;
         }
         Ok((to - from) as usize)
     }
 
+    fn set_max_retained_entries(&self, raft_group_id: u64, max: u64) {
+        RocksEngine::set_max_retained_entries(self, raft_group_id, max);
+    }
+
     fn purge_expired_files(&self) -> Result<Vec<u64>> {
         Ok(vec![])
     }
@@ -222,6 +363,25 @@ impl RaftEngine for RocksEngine {
         MiscExt::dump_stats(self)
     }
 
+    fn wal_stats(&self) -> Result<WalStats> {
+        // RocksDB recycles WALs in place and doesn't expose a recycled file
+        // count, so only the size is reported. WALs living in a custom
+        // wal-dir are not visible from the db path and are skipped.
+        let mut stats = WalStats::default();
+        let dir = match std::fs::read_dir(self.path()) {
+            Ok(dir) => dir,
+            Err(_) => return Ok(stats),
+        };
+        for f in dir.flatten() {
+            if f.path().extension().map_or(false, |e| e == "log") {
+                if let Ok(meta) = f.metadata() {
+                    stats.total_size += meta.len();
+                }
+            }
+        }
+        Ok(stats)
+    }
+
     fn get_engine_size(&self) -> Result<u64> {
         let handle = util::get_cf_handle(self.as_inner(), CF_DEFAULT)?;
         let used_size = util::get_engine_cf_used_size(self.as_inner(), handle);
@@ -230,11 +390,179 @@ impl RaftEngine for RocksEngine {
     }
 }
 
+impl RaftEngineDebug for RocksEngine {
+    fn find_log_holes(&self, raft_group_id: u64) -> Result<Vec<(u64, u64)>> {
+        let start_key = keys::raft_log_key(raft_group_id, 0);
+        let end_key = keys::raft_log_key(raft_group_id, u64::MAX);
+        let mut holes = Vec::new();
+        let mut last_index: Option<u64> = None;
+        self.scan(
+            &start_key,
+            &end_key,
+            false, // fill_cache
+            |key, _| {
+                let index = box_try!(keys::raft_log_index(key));
+                if let Some(last) = last_index {
+                    if index > last + 1 {
+                        holes.push((last + 1, index - 1));
+                    }
+                }
+                last_index = Some(index);
+                Ok(true)
+            },
+        )?;
+        Ok(holes)
+    }
+
+    fn check_region_consistency(
+        &self,
+        raft_group_id: u64,
+        apply_state: &RaftApplyState,
+    ) -> Result<()> {
+        let raft_state = self.get_raft_state(raft_group_id)?.ok_or_else(|| {
+            Error::Other(box_err!("[region {}] raft state is missing", raft_group_id))
+        })?;
+        let last_index = raft_state.get_last_index();
+        let truncated_index = apply_state.get_truncated_state().get_index();
+        if last_index < truncated_index {
+            return Err(box_err!(
+                "[region {}] last index {} is smaller than truncated index {}",
+                raft_group_id,
+                last_index,
+                truncated_index
+            ));
+        }
+        let applied_index = apply_state.get_applied_index();
+        if applied_index > last_index {
+            return Err(box_err!(
+                "[region {}] applied index {} exceeds last index {}",
+                raft_group_id,
+                applied_index,
+                last_index
+            ));
+        }
+        // Entries in (truncated_index, last_index] must be stored contiguously.
+        let mut expected = truncated_index + 1;
+        let start_key = keys::raft_log_key(raft_group_id, expected);
+        let end_key = keys::raft_log_key(raft_group_id, last_index + 1);
+        self.scan(
+            &start_key,
+            &end_key,
+            false, // fill_cache
+            |key, _| {
+                let index = box_try!(keys::raft_log_index(key));
+                if index != expected {
+                    return Err(box_err!(
+                        "[region {}] log entry {} is missing, next stored index is {}",
+                        raft_group_id,
+                        expected,
+                        index
+                    ));
+                }
+                expected += 1;
+                Ok(true)
+            },
+        )?;
+        if expected != last_index + 1 {
+            return Err(box_err!(
+                "[region {}] log entries [{}, {}] are missing",
+                raft_group_id,
+                expected,
+                last_index
+            ));
+        }
+        Ok(())
+    }
+
+    fn rewrite_region(&self, raft_group_id: u64) -> Result<()> {
+        // RocksDB has no notion of rewriting a key range in place, but a
+        // manual compaction of the region's log keys drops the tombstones
+        // and overwritten versions, which is the same space back.
+        let before = RaftEngine::get_engine_size(self)?;
+        let start_key = keys::raft_log_key(raft_group_id, 0);
+        let end_key = keys::raft_log_key(raft_group_id, u64::MAX);
+        self.compact_range(
+            CF_DEFAULT,
+            Some(&start_key),
+            Some(&end_key),
+            false, // exclusive_manual
+            1,     // max_subcompactions
+        )?;
+        let after = RaftEngine::get_engine_size(self)?;
+        RAFT_LOG_REWRITE_RECLAIMED_BYTES.inc_by(before.saturating_sub(after));
+        Ok(())
+    }
+}
+
+impl RocksEngine {
+    /// Decodes a raw raft log value read from this engine, respecting its
+    /// checksum flag. For callers that scan raftdb values directly, e.g.
+    /// the raft engine migration, instead of going through `get_entry`.
+    pub fn decode_raft_log_value(&self, value: &[u8]) -> Result<Entry> {
+        decode_entry(value, self.raft_log_checksum_enabled())
+    }
+
+    /// Deletes all log entries of `raft_group_id` with index >= `from_index`
+    /// and records the rewound `last_index` in `batch`, which the caller has
+    /// to consume. This is the opposite of `gc`: a tail truncation used by
+    /// offline repair tools to rewind a diverged replica. Returns the count
+    /// of deleted entries.
+    ///
+    /// The commit index is clamped as well, since a rewound log can't keep a
+    /// commit record beyond its new end.
+    pub fn truncate_suffix(
+        &self,
+        raft_group_id: u64,
+        from_index: u64,
+        batch: &mut RocksWriteBatch,
+    ) -> Result<usize> {
+        if from_index == 0 {
+            return Err(box_err!("truncate_suffix can't remove the whole log"));
+        }
+        let mut state = self.get_raft_state(raft_group_id)?.ok_or_else(|| {
+            Error::Other(box_err!("[region {}] raft state is missing", raft_group_id))
+        })?;
+        let last_index = state.get_last_index();
+        if from_index > last_index {
+            return Ok(0);
+        }
+        for index in from_index..=last_index {
+            batch.delete(&keys::raft_log_key(raft_group_id, index))?;
+        }
+        state.set_last_index(from_index - 1);
+        if state.get_hard_state().get_commit() >= from_index {
+            state.mut_hard_state().set_commit(from_index - 1);
+        }
+        batch.put_raft_state(raft_group_id, &state)?;
+        Ok((last_index - from_index + 1) as usize)
+    }
+}
+
 impl RaftLogBatch for RocksWriteBatch {
     fn append(&mut self, raft_group_id: u64, entries: Vec<Entry>) -> Result<()> {
         if let Some(max_size) = entries.iter().map(|e| e.compute_size()).max() {
-            let ser_buf = Vec::with_capacity(max_size as usize);
-            return self.append_impl(raft_group_id, &entries, ser_buf);
+            let mut ser_buf = Vec::with_capacity(max_size as usize);
+            return self.append_impl(raft_group_id, &entries, &mut ser_buf);
+        }
+        Ok(())
+    }
+
+    fn append_multi(&mut self, groups: Vec<(u64, Option<u64>, Vec<Entry>)>) -> Result<()> {
+        // One buffer sized from the global max entry serves all groups.
+        let max_size = groups
+            .iter()
+            .flat_map(|(_, _, entries)| entries.iter())
+            .map(|e| e.compute_size())
+            .max();
+        let mut ser_buf = Vec::with_capacity(max_size.unwrap_or(0) as usize);
+        for (raft_group_id, prev_last_index, entries) in groups {
+            let last_index = entries.last().map(|e| e.get_index());
+            self.append_impl(raft_group_id, &entries, &mut ser_buf)?;
+            if let (Some(prev), Some(last)) = (prev_last_index, last_index) {
+                if prev > last {
+                    self.cut_logs(raft_group_id, last + 1, prev + 1);
+                }
+            }
         }
         Ok(())
     }
@@ -250,24 +578,568 @@ impl RaftLogBatch for RocksWriteBatch {
         self.put_msg(&keys::raft_state_key(raft_group_id), state)
     }
 
+    fn reserve(&mut self, entries: usize, bytes: usize) {
+        // Besides the serialized entries, the batch stores a log key and a
+        // record header per entry.
+        const RECORD_OVERHEAD: usize = 32;
+        RocksWriteBatch::reserve(self, bytes + entries * RECORD_OVERHEAD);
+    }
+
     fn is_empty(&self) -> bool {
         WriteBatch::is_empty(self)
     }
 }
 
+/// A prototype pipelined writer for Raft log batches.
+///
+/// Batches submitted through `consume_async` are written by a dedicated
+/// background thread strictly in submission order, so the submitting thread
+/// can keep building the next batch while the previous one is being written
+/// and synced.
+pub struct RocksAsyncLogWriter {
+    sender: Option<std::sync::mpsc::Sender<AsyncLogTask>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+struct AsyncLogTask {
+    batch: RocksWriteBatch,
+    sync: bool,
+    result_tx: std::sync::mpsc::Sender<Result<usize>>,
+}
+
+impl RocksAsyncLogWriter {
+    pub fn new(engine: RocksEngine) -> RocksAsyncLogWriter {
+        let (sender, receiver) = std::sync::mpsc::channel::<AsyncLogTask>();
+        let handle = std::thread::Builder::new()
+            .name("raft-log-writer".to_owned())
+            .spawn(move || {
+                while let Ok(mut task) = receiver.recv() {
+                    let res = engine.consume(&mut task.batch, task.sync);
+                    // The submitter may have dropped the receiver.
+                    let _ = task.result_tx.send(res);
+                }
+            })
+            .unwrap();
+        RocksAsyncLogWriter {
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+
+    /// Submits `batch` to the background writer and returns a receiver that
+    /// resolves with the written bytes once the write, and the WAL sync if
+    /// `sync` is set, has completed.
+    pub fn consume_async(
+        &self,
+        batch: RocksWriteBatch,
+        sync: bool,
+    ) -> std::sync::mpsc::Receiver<Result<usize>> {
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(AsyncLogTask {
+                batch,
+                sync,
+                result_tx,
+            })
+            .unwrap();
+        result_rx
+    }
+}
+
+impl Drop for RocksAsyncLogWriter {
+    fn drop(&mut self) {
+        // Closing the channel stops the writer thread after it drains all
+        // submitted batches.
+        self.sender.take();
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
 impl RocksWriteBatch {
+
     fn append_impl(
         &mut self,
         raft_group_id: u64,
         entries: &[Entry],
-        mut ser_buf: Vec<u8>,
+        ser_buf: &mut Vec<u8>,
     ) -> Result<()> {
         for entry in entries {
             let key = keys::raft_log_key(raft_group_id, entry.get_index());
             ser_buf.clear();
             entry.write_to_vec(&mut ser_buf).unwrap();
+            if self.raft_log_checksum_enabled() {
+                let mut digest = crc32fast::Hasher::new();
+                digest.update(&ser_buf);
+                ser_buf.extend_from_slice(&digest.finalize().to_le_bytes());
+            }
             self.put(&key, &ser_buf)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::new_default_engine;
+    use tempfile::Builder;
+
+    fn append_entries(engine: &RocksEngine, raft_group_id: u64, indexes: &[u64]) {
+        let entries = indexes
+            .iter()
+            .map(|i| {
+                let mut e = Entry::default();
+                e.set_index(*i);
+                e
+            })
+            .collect();
+        RaftEngine::append(engine, raft_group_id, entries).unwrap();
+    }
+
+    #[test]
+    fn test_log_checksum_round_trip() {
+        let dir = Builder::new()
+            .prefix("test_log_checksum_round_trip")
+            .tempdir()
+            .unwrap();
+        let mut engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+        engine.set_raft_log_checksum(true);
+
+        append_entries(&engine, 1, &[1, 2, 3]);
+        assert_eq!(engine.get_entry(1, 2).unwrap().unwrap().get_index(), 2);
+        let mut entries = Vec::new();
+        assert_eq!(engine.fetch_entries_to(1, 1, 4, None, &mut entries).unwrap(), 3);
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[test]
+    fn test_fetch_entries_records_duration() {
+        let dir = Builder::new()
+            .prefix("test_fetch_entries_records_duration")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+        let indexes: Vec<_> = (1..=20).collect();
+        append_entries(&engine, 1, &indexes);
+
+        let multi_get = RAFT_LOG_FETCH_DURATION_HISTOGRAM.with_label_values(&["multi_get"]);
+        let scan = RAFT_LOG_FETCH_DURATION_HISTOGRAM.with_label_values(&["scan"]);
+        let (multi_get_before, scan_before) =
+            (multi_get.get_sample_count(), scan.get_sample_count());
+
+        // A short range takes the multi-get fast path, a long one the scan
+        // path, and each must record exactly one sample.
+        let mut entries = Vec::new();
+        engine.fetch_entries_to(1, 1, 4, None, &mut entries).unwrap();
+        assert_eq!(multi_get.get_sample_count(), multi_get_before + 1);
+        assert_eq!(scan.get_sample_count(), scan_before);
+        entries.clear();
+        engine.fetch_entries_to(1, 1, 21, None, &mut entries).unwrap();
+        assert_eq!(multi_get.get_sample_count(), multi_get_before + 1);
+        assert_eq!(scan.get_sample_count(), scan_before + 1);
+    }
+
+    #[test]
+    fn test_first_index_after_gc() {
+        let dir = Builder::new()
+            .prefix("test_first_index_after_gc")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(engine.first_index(1).unwrap(), None);
+
+        let indexes: Vec<_> = (1..=10).collect();
+        append_entries(&engine, 1, &indexes);
+        assert_eq!(engine.first_index(1).unwrap(), Some(1));
+
+        engine.gc(1, 1, 6).unwrap();
+        assert_eq!(engine.first_index(1).unwrap(), Some(6));
+        // Other regions are not affected.
+        assert_eq!(engine.first_index(2).unwrap(), None);
+
+        engine.gc(1, 6, 11).unwrap();
+        assert_eq!(engine.first_index(1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rewrite_region_keeps_live_log() {
+        let dir = Builder::new()
+            .prefix("test_rewrite_region_keeps_live_log")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+        let indexes: Vec<_> = (1..=100).collect();
+        append_entries(&engine, 1, &indexes);
+        engine.gc(1, 1, 90).unwrap();
+
+        engine.rewrite_region(1).unwrap();
+        // The live tail must survive the rewrite and the log stays
+        // continuous.
+        let mut entries = Vec::new();
+        assert_eq!(
+            engine.fetch_entries_to(1, 90, 101, None, &mut entries).unwrap(),
+            11
+        );
+        assert!(RaftEngineDebug::find_log_holes(&engine, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_log_checksum_corruption() {
+        let dir = Builder::new()
+            .prefix("test_log_checksum_corruption")
+            .tempdir()
+            .unwrap();
+        let mut engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+        engine.set_raft_log_checksum(true);
+
+        append_entries(&engine, 1, &[1, 2, 3]);
+        // Flip one bit of the stored value, keeping the trailing CRC intact.
+        let key = keys::raft_log_key(1, 2);
+        let mut value = engine.get_value(&key).unwrap().unwrap().to_vec();
+        value[0] ^= 0x1;
+        engine.put(&key, &value).unwrap();
+
+        match engine.get_entry(1, 2) {
+            Err(Error::ChecksumMismatch) => (),
+            other => panic!("expect checksum mismatch, got {:?}", other),
+        }
+        let mut entries = Vec::new();
+        match engine.fetch_entries_to(1, 1, 4, None, &mut entries) {
+            Err(_) => (),
+            other => panic!("expect checksum mismatch, got {:?}", other),
+        }
+        // Intact entries are still readable.
+        assert_eq!(engine.get_entry(1, 3).unwrap().unwrap().get_index(), 3);
+    }
+
+    #[test]
+    fn test_max_retained_entries() {
+        let dir = Builder::new()
+            .prefix("test_max_retained_entries")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        let indexes: Vec<_> = (1..=30).collect();
+        append_entries(&engine, 1, &indexes);
+        let mut state = RaftLocalState::default();
+        state.set_last_index(30);
+        engine.put_raft_state(1, &state).unwrap();
+
+        // Unbounded by default: gc only honors the requested range.
+        engine.gc(1, 0, 5).unwrap();
+        assert!(engine.get_entry(1, 5).unwrap().is_some());
+
+        // The cap never extends deletion past the caller's bound: entries
+        // at `to` and above may not be applied yet, so they must survive.
+        engine.set_max_retained_entries(1, 10);
+        engine.gc(1, 0, 6).unwrap();
+        assert!(engine.get_entry(1, 5).unwrap().is_none());
+        assert!(engine.get_entry(1, 6).unwrap().is_some());
+        assert!(engine.get_entry(1, 30).unwrap().is_some());
+
+        // An empty requested range deletes nothing, cap or not.
+        let indexes: Vec<_> = (31..=40).collect();
+        append_entries(&engine, 1, &indexes);
+        state.set_last_index(40);
+        engine.put_raft_state(1, &state).unwrap();
+        engine.gc(1, 0, 0).unwrap();
+        assert!(engine.get_entry(1, 6).unwrap().is_some());
+        assert!(engine.get_entry(1, 40).unwrap().is_some());
+
+        // Resetting to 0 removes the cap.
+        engine.set_max_retained_entries(1, 0);
+        engine.gc(1, 0, 0).unwrap();
+        assert!(engine.get_entry(1, 6).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_for_each_entry() {
+        let dir = Builder::new()
+            .prefix("test_for_each_entry")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        append_entries(&engine, 1, &[5, 6, 7, 8, 9, 10]);
+
+        // A full pass visits the same entries as `fetch_entries_to`.
+        let mut fetched = Vec::new();
+        engine.fetch_entries_to(1, 5, 11, None, &mut fetched).unwrap();
+        let mut visited = Vec::new();
+        engine
+            .for_each_entry(1, 5, 11, |e| {
+                visited.push(e.clone());
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(visited, fetched);
+
+        // The closure can stop the iteration early.
+        let mut count = 0;
+        engine
+            .for_each_entry(1, 5, 11, |_| {
+                count += 1;
+                Ok(count < 3)
+            })
+            .unwrap();
+        assert_eq!(count, 3);
+
+        // Missing head or hole in the range surfaces as an error.
+        match engine.for_each_entry(1, 3, 11, |_| Ok(true)) {
+            Err(Error::EntriesCompacted) => (),
+            other => panic!("expect compacted error, got {:?}", other),
+        }
+        append_entries(&engine, 2, &[5, 6, 8]);
+        match engine.for_each_entry(2, 5, 9, |_| Ok(true)) {
+            Err(Error::EntriesUnavailable) => (),
+            other => panic!("expect unavailable error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_find_log_holes() {
+        let dir = Builder::new()
+            .prefix("test_find_log_holes")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        // Empty log has no holes.
+        assert!(engine.find_log_holes(1).unwrap().is_empty());
+
+        // Continuous log has no holes.
+        append_entries(&engine, 1, &[5, 6, 7, 8]);
+        assert!(engine.find_log_holes(1).unwrap().is_empty());
+
+        // Punch two holes: [9, 9] and [11, 19].
+        append_entries(&engine, 2, &[5, 6, 7, 8, 10, 20, 21]);
+        assert_eq!(engine.find_log_holes(2).unwrap(), vec![(9, 9), (11, 19)]);
+
+        // Logs of other regions don't interfere.
+        assert!(engine.find_log_holes(1).unwrap().is_empty());
+        assert!(engine.find_log_holes(3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_truncate_suffix() {
+        let dir = Builder::new()
+            .prefix("test_truncate_suffix")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        let mut raft_state = RaftLocalState::default();
+        raft_state.set_last_index(10);
+        raft_state.mut_hard_state().set_commit(9);
+        engine.put_raft_state(1, &raft_state).unwrap();
+        append_entries(&engine, 1, &[5, 6, 7, 8, 9, 10]);
+
+        let mut batch = engine.log_batch(0);
+        assert_eq!(engine.truncate_suffix(1, 8, &mut batch).unwrap(), 3);
+        engine.consume(&mut batch, true).unwrap();
+
+        // The surviving log is exactly [5, 8).
+        for index in 5..8 {
+            assert!(engine.get_entry(1, index).unwrap().is_some());
+        }
+        for index in 8..=10 {
+            assert!(engine.get_entry(1, index).unwrap().is_none());
+        }
+        let state = engine.get_raft_state(1).unwrap().unwrap();
+        assert_eq!(state.get_last_index(), 7);
+        // The commit index never points beyond the rewound end.
+        assert_eq!(state.get_hard_state().get_commit(), 7);
+
+        // Rewinding beyond the end is a no-op.
+        let mut batch = engine.log_batch(0);
+        assert_eq!(engine.truncate_suffix(1, 8, &mut batch).unwrap(), 0);
+        assert!(RaftLogBatch::is_empty(&batch));
+
+        // An unknown region and a full wipe are rejected.
+        engine.truncate_suffix(2, 8, &mut batch).unwrap_err();
+        engine.truncate_suffix(1, 0, &mut batch).unwrap_err();
+    }
+
+    #[test]
+    fn test_check_region_consistency() {
+        let dir = Builder::new()
+            .prefix("test_check_region_consistency")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        let mut raft_state = RaftLocalState::default();
+        raft_state.set_last_index(10);
+        engine.put_raft_state(1, &raft_state).unwrap();
+        append_entries(&engine, 1, &[6, 7, 8, 9, 10]);
+
+        let mut apply_state = RaftApplyState::default();
+        apply_state.set_applied_index(8);
+        apply_state.mut_truncated_state().set_index(5);
+        engine.check_region_consistency(1, &apply_state).unwrap();
+
+        // Missing raft state.
+        engine.check_region_consistency(2, &apply_state).unwrap_err();
+
+        // Applied index beyond last index.
+        apply_state.set_applied_index(11);
+        engine.check_region_consistency(1, &apply_state).unwrap_err();
+        apply_state.set_applied_index(8);
+
+        // Last index falls behind the truncated index.
+        apply_state.mut_truncated_state().set_index(11);
+        engine.check_region_consistency(1, &apply_state).unwrap_err();
+        apply_state.mut_truncated_state().set_index(5);
+
+        // A missing tail and a hole in the middle are both reported.
+        engine.delete(&keys::raft_log_key(1, 10)).unwrap();
+        engine.check_region_consistency(1, &apply_state).unwrap_err();
+        engine.delete(&keys::raft_log_key(1, 8)).unwrap();
+        engine.check_region_consistency(1, &apply_state).unwrap_err();
+    }
+
+    #[test]
+    fn test_append_multi() {
+        let dir = Builder::new()
+            .prefix("test_append_multi")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        let entries = |indexes: &[u64]| -> Vec<Entry> {
+            indexes
+                .iter()
+                .map(|i| {
+                    let mut e = Entry::default();
+                    e.set_index(*i);
+                    e.set_data(vec![0; *i as usize].into());
+                    e
+                })
+                .collect()
+        };
+
+        // Region 2 already has logs up to index 8, appending up to 6 must cut
+        // the overwritten tail.
+        append_entries(&engine, 2, &[5, 6, 7, 8]);
+
+        let mut batch = engine.log_batch(0);
+        batch
+            .append_multi(vec![
+                (1, None, entries(&[1, 2, 3])),
+                (2, Some(8), entries(&[5, 6])),
+                (3, None, vec![]),
+            ])
+            .unwrap();
+        engine.consume(&mut batch, false).unwrap();
+
+        let mut fetched = Vec::new();
+        assert_eq!(engine.fetch_entries_to(1, 1, 4, None, &mut fetched).unwrap(), 3);
+        assert_eq!(engine.get_entry(2, 6).unwrap().unwrap().get_index(), 6);
+        assert!(engine.get_entry(2, 7).unwrap().is_none());
+        assert!(engine.get_entry(2, 8).unwrap().is_none());
+        assert!(engine.get_entry(3, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_clean_multi_regions() {
+        let dir = Builder::new()
+            .prefix("test_clean_multi_regions")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        let mut regions = Vec::new();
+        for id in 1..=10 {
+            append_entries(&engine, id, &[1, 2, 3, 4, 5]);
+            let mut state = RaftLocalState::default();
+            state.set_last_index(5);
+            engine.put_raft_state(id, &state).unwrap();
+            regions.push((id, state));
+        }
+
+        let mut batch = engine.log_batch(0);
+        engine.clean_multi(&regions, &mut batch).unwrap();
+        engine.consume(&mut batch, false).unwrap();
+
+        for id in 1..=10 {
+            assert!(engine.get_raft_state(id).unwrap().is_none());
+            for index in 1..=5 {
+                assert!(engine.get_entry(id, index).unwrap().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_region_log_span() {
+        let dir = Builder::new()
+            .prefix("test_region_log_span")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+
+        // No stored log at all.
+        assert_eq!(engine.region_log_span(1).unwrap(), None);
+
+        let indexes: Vec<_> = (1..=10).collect();
+        append_entries(&engine, 1, &indexes);
+        let mut state = RaftLocalState::default();
+        state.set_last_index(10);
+        engine.put_raft_state(1, &state).unwrap();
+        assert_eq!(engine.region_log_span(1).unwrap(), Some((1, 10)));
+
+        // GC moves the lower bound of the span.
+        engine.gc(1, 1, 6).unwrap();
+        assert_eq!(engine.region_log_span(1).unwrap(), Some((6, 10)));
+
+        // A fully compacted log has no span again.
+        engine.gc(1, 6, 11).unwrap();
+        assert_eq!(engine.region_log_span(1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_replace_region_log() {
+        let dir = Builder::new()
+            .prefix("test_replace_region_log")
+            .tempdir()
+            .unwrap();
+        let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+        let indexes: Vec<_> = (1..=10).collect();
+        append_entries(&engine, 1, &indexes);
+        let mut state = RaftLocalState::default();
+        state.set_last_index(10);
+        engine.put_raft_state(1, &state).unwrap();
+
+        // Swap the whole log for the known-good entries [4, 6].
+        let entries: Vec<_> = (4..=6)
+            .map(|i| {
+                let mut e = Entry::default();
+                e.set_index(i);
+                e.set_term(2);
+                e
+            })
+            .collect();
+        let mut new_state = RaftLocalState::default();
+        new_state.set_last_index(6);
+        let mut batch = engine.log_batch(0);
+        engine
+            .replace_region_log(1, entries.clone(), &new_state, &mut batch)
+            .unwrap();
+        engine.consume(&mut batch, true).unwrap();
+
+        assert_eq!(engine.get_raft_state(1).unwrap().unwrap(), new_state);
+        assert_eq!(engine.region_log_span(1).unwrap(), Some((4, 6)));
+        let mut fetched = Vec::new();
+        engine
+            .fetch_entries_to(1, 4, 7, None, &mut fetched)
+            .unwrap();
+        assert_eq!(fetched, entries);
+        for index in (1..4).chain(7..11) {
+            assert!(engine.get_entry(1, index).unwrap().is_none());
+        }
+    }
+}
@@ -161,6 +161,12 @@ pub fn get_cf_compaction_pending_bytes(engine: &DB, handle: &CFHandle) -> Option
     engine.get_property_int_cf(handle, ROCKSDB_PENDING_COMPACTION_BYTES)
 }
 
+/// Gets the total size of obsolete blob files waiting to be collected by
+/// Titan GC of given column family.
+pub fn get_cf_blob_gc_pending_bytes(engine: &DB, handle: &CFHandle) -> Option<u64> {
+    engine.get_property_int_cf(handle, ROCKSDB_TITANDB_OBSOLETE_BLOB_FILE_SIZE)
+}
+
 pub struct FixedSuffixSliceTransform {
     pub suffix_len: usize,
 }
@@ -34,6 +34,7 @@ impl WriteBatchExt for RocksEngine {
 pub struct RocksWriteBatch {
     db: Arc<DB>,
     wb: RawWriteBatch,
+    raft_log_checksum: bool,
 }
 
 impl RocksWriteBatch {
@@ -41,6 +42,7 @@ impl RocksWriteBatch {
         RocksWriteBatch {
             db,
             wb: RawWriteBatch::default(),
+            raft_log_checksum: false,
         }
     }
 
@@ -54,16 +56,43 @@ impl RocksWriteBatch {
         } else {
             RawWriteBatch::with_capacity(cap)
         };
-        RocksWriteBatch { db, wb }
+        RocksWriteBatch {
+            db,
+            wb,
+            raft_log_checksum: false,
+        }
     }
 
     pub fn from_raw(db: Arc<DB>, wb: RawWriteBatch) -> RocksWriteBatch {
-        RocksWriteBatch { db, wb }
+        RocksWriteBatch {
+            db,
+            wb,
+            raft_log_checksum: false,
+        }
     }
 
     pub fn get_db(&self) -> &DB {
         self.db.as_ref()
     }
+
+    /// Makes `RaftLogBatch::append` attach a CRC32 to every entry it writes.
+    /// It's set by `RaftEngine::log_batch` according to the engine option.
+    pub fn set_raft_log_checksum(&mut self, enable: bool) {
+        self.raft_log_checksum = enable;
+    }
+
+    pub(crate) fn raft_log_checksum_enabled(&self) -> bool {
+        self.raft_log_checksum
+    }
+
+    /// Pre-sizes the underlying write batch buffer for `bytes` of data.
+    /// RocksDB only allows sizing a batch at construction, so this swaps in
+    /// a pre-sized batch and is a no-op once the batch holds data.
+    pub fn reserve(&mut self, bytes: usize) {
+        if bytes > 0 && self.wb.is_empty() {
+            self.wb = RawWriteBatch::with_capacity(bytes);
+        }
+    }
 }
 
 impl engine_traits::WriteBatch<RocksEngine> for RocksWriteBatch {
@@ -36,6 +36,8 @@ pub enum Error {
     EntriesUnavailable,
     #[error("The entries of region is compacted")]
     EntriesCompacted,
+    #[error("Checksum of the stored value mismatches")]
+    ChecksumMismatch,
 }
 
 impl From<String> for Error {
@@ -58,6 +60,7 @@ impl ErrorCodeExt for Error {
             Error::Other(_) => error_code::UNKNOWN,
             Error::EntriesUnavailable => error_code::engine::DATALOSS,
             Error::EntriesCompacted => error_code::engine::DATACOMPACTED,
+            Error::ChecksumMismatch => error_code::engine::CHECKSUM_MISMATCH,
         }
     }
 }
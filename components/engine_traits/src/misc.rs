@@ -87,5 +87,7 @@ pub trait MiscExt: CFNamesExt {
 
     fn get_cf_compaction_pending_bytes(&self, cf: &str) -> Result<Option<u64>>;
 
+    fn get_cf_blob_gc_pending_bytes(&self, cf: &str) -> Result<Option<u64>>;
+
     fn is_stalled_or_stopped(&self) -> bool;
 }
@@ -1,7 +1,7 @@
 // Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
 
 use crate::*;
-use kvproto::raft_serverpb::RaftLocalState;
+use kvproto::raft_serverpb::{RaftApplyState, RaftLocalState};
 use raft::eraftpb::Entry;
 
 pub trait RaftEngineReadOnly: Sync + Send + 'static {
@@ -9,6 +9,17 @@ pub trait RaftEngineReadOnly: Sync + Send + 'static {
 
     fn get_entry(&self, raft_group_id: u64, index: u64) -> Result<Option<Entry>>;
 
+    /// Return the lowest stored log index of the raft group, or `None` if it
+    /// has no stored entries. Lets callers clamp reads to the available
+    /// range instead of probing with `get_entry` and catching
+    /// `EntriesCompacted`.
+    fn first_index(&self, raft_group_id: u64) -> Result<Option<u64>>;
+
+    /// Return `(first_index, last_index)` of the raft group's stored log, or
+    /// `None` if it has no stored entries. Lets tooling see at a glance how
+    /// far log GC lags behind for each region.
+    fn region_log_span(&self, raft_group_id: u64) -> Result<Option<(u64, u64)>>;
+
     /// Return count of fetched entries.
     fn fetch_entries_to(
         &self,
@@ -18,6 +29,61 @@ pub trait RaftEngineReadOnly: Sync + Send + 'static {
         max_size: Option<usize>,
         to: &mut Vec<Entry>,
     ) -> Result<usize>;
+
+    /// Iterate over entries in `[low, high)` and invoke `f` on each, stopping
+    /// early when `f` returns `Ok(false)`. Returns an error if entries in the
+    /// range are missing, e.g. compacted away.
+    ///
+    /// The default implementation buffers the whole range with
+    /// `fetch_entries_to`; engines with a native scan override it to stream
+    /// entries without the allocation.
+    fn for_each_entry<F>(&self, raft_group_id: u64, low: u64, high: u64, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Entry) -> Result<bool>,
+    {
+        let mut entries = Vec::with_capacity((high - low) as usize);
+        self.fetch_entries_to(raft_group_id, low, high, None, &mut entries)?;
+        for e in &entries {
+            if !f(e)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Debugging facilities of a Raft engine. These are not used on hot paths,
+/// only by diagnostic tools.
+pub trait RaftEngineDebug: RaftEngine {
+    /// Scan the stored log of `raft_group_id` and return the missing index
+    /// ranges (both ends inclusive) between the first and last stored entries.
+    ///
+    /// An empty result means the log is continuous. It's useful to diagnose
+    /// log corruption after an unclean shutdown.
+    fn find_log_holes(&self, raft_group_id: u64) -> Result<Vec<(u64, u64)>>;
+
+    /// Verify the stored state of `raft_group_id` is internally consistent:
+    /// `last_index` is no less than the truncated index, `applied_index`
+    /// doesn't exceed `last_index`, and log entries exist contiguously from
+    /// the truncated index up to `last_index`.
+    ///
+    /// The apply state is persisted in the kv engine, so the caller has to
+    /// supply it. Returns a descriptive error for the first inconsistency.
+    fn check_region_consistency(
+        &self,
+        raft_group_id: u64,
+        apply_state: &RaftApplyState,
+    ) -> Result<()>;
+
+    /// Rewrite the stored log of `raft_group_id` so its live data is
+    /// consolidated and the space held by stale copies can be reclaimed,
+    /// without purging or compacting anything else.
+    ///
+    /// Intended for an operator to reclaim space pinned by a single
+    /// pathological region, e.g. one whose entries are spread across many
+    /// log files after heavy GC. Engines without a native rewrite fall back
+    /// to compacting the region's key range.
+    fn rewrite_region(&self, raft_group_id: u64) -> Result<()>;
 }
 
 pub trait RaftEngine: RaftEngineReadOnly + Clone + Sync + Send + 'static {
@@ -48,6 +114,39 @@ pub trait RaftEngine: RaftEngineReadOnly + Clone + Sync + Send + 'static {
         batch: &mut Self::LogBatch,
     ) -> Result<()>;
 
+    /// Cleans several raft groups' state and logs into one shared `batch`,
+    /// so bulk cleanup (e.g. after a store is decommissioned) pays for a
+    /// single write instead of one per region. Behavior equals calling
+    /// `clean` for each region.
+    fn clean_multi(
+        &self,
+        regions: &[(u64, RaftLocalState)],
+        batch: &mut Self::LogBatch,
+    ) -> Result<()> {
+        for (raft_group_id, state) in regions {
+            self.clean(*raft_group_id, state, batch)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the region's stored log wholesale: deletes all existing
+    /// entries and writes `entries` plus the new `RaftLocalState` into
+    /// `batch`, so consuming the batch swaps the log in one write. Meant
+    /// for offline repair tools restoring a known-good log.
+    fn replace_region_log(
+        &self,
+        raft_group_id: u64,
+        entries: Vec<Entry>,
+        state: &RaftLocalState,
+        batch: &mut Self::LogBatch,
+    ) -> Result<()> {
+        if let Some((first, last)) = self.region_log_span(raft_group_id)? {
+            batch.cut_logs(raft_group_id, first, last + 1);
+        }
+        batch.append(raft_group_id, entries)?;
+        batch.put_raft_state(raft_group_id, state)
+    }
+
     /// Append some log entries and return written bytes.
     ///
     /// Note: `RaftLocalState` won't be updated in this call.
@@ -59,6 +158,16 @@ pub trait RaftEngine: RaftEngineReadOnly + Clone + Sync + Send + 'static {
     /// Generally, `from` can be passed in `0`.
     fn gc(&self, raft_group_id: u64, from: u64, to: u64) -> Result<usize>;
 
+    /// Caps the number of log entries retained for the raft group: `gc`
+    /// trims the log further so that at most `max` entries up to the last
+    /// stored index remain, but never deletes past the range the caller
+    /// authorized — entries beyond it may not be applied yet. 0 (the
+    /// default) keeps the log unbounded.
+    ///
+    /// Intended for memory-bounded test setups. Engines that don't support
+    /// it ignore the cap.
+    fn set_max_retained_entries(&self, _raft_group_id: u64, _max: u64) {}
+
     /// Purge expired logs files and return a set of Raft group ids
     /// which needs to be compacted ASAP.
     fn purge_expired_files(&self) -> Result<Vec<u64>>;
@@ -75,6 +184,12 @@ pub trait RaftEngine: RaftEngineReadOnly + Clone + Sync + Send + 'static {
     fn flush_stats(&self) -> Option<CacheStats> {
         None
     }
+
+    /// Statistics of the write-ahead log for capacity monitoring. Engines
+    /// that can't report them return zeros rather than failing.
+    fn wal_stats(&self) -> Result<WalStats> {
+        Ok(WalStats::default())
+    }
     fn reset_statistics(&self) {}
 
     fn stop(&self) {}
@@ -91,11 +206,47 @@ pub trait RaftLogBatch: Send {
     /// Remove Raft logs in [`from`, `to`) which will be overwritten later.
     fn cut_logs(&mut self, raft_group_id: u64, from: u64, to: u64);
 
+    /// Appends several raft groups' entries in one call. Each group is a
+    /// `(raft_group_id, prev_last_index, entries)` tuple; when
+    /// `prev_last_index` is given and exceeds the last appended index, the
+    /// overwritten tail is removed with `cut_logs`. The observable effect
+    /// equals calling `append` (and `cut_logs`) per group, but engines may
+    /// override it to share serialization state across groups.
+    fn append_multi(&mut self, groups: Vec<(u64, Option<u64>, Vec<Entry>)>) -> Result<()> {
+        for (raft_group_id, prev_last_index, entries) in groups {
+            let last_index = entries.last().map(|e| e.get_index());
+            self.append(raft_group_id, entries)?;
+            if let (Some(prev), Some(last)) = (prev_last_index, last_index) {
+                if prev > last {
+                    self.cut_logs(raft_group_id, last + 1, prev + 1);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn put_raft_state(&mut self, raft_group_id: u64, state: &RaftLocalState) -> Result<()>;
 
+    /// Hints that the batch will hold about `entries` entries totalling
+    /// `bytes` serialized bytes, so the engine can reserve capacity up front
+    /// instead of reallocating while the batch grows. Callers building large
+    /// batches, e.g. for snapshot apply, call it before the first `append`.
+    ///
+    /// It's only a hint: engines that don't benefit ignore it.
+    fn reserve(&mut self, _entries: usize, _bytes: usize) {}
+
     fn is_empty(&self) -> bool;
 }
 
+/// Statistics of an engine's write-ahead log.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct WalStats {
+    /// Total size of live WAL files in bytes.
+    pub total_size: u64,
+    /// Number of log files kept around for recycling.
+    pub recycled_file_count: u64,
+}
+
 #[derive(Clone, Copy, Default)]
 pub struct CacheStats {
     pub hit: usize,
@@ -10,5 +10,6 @@ define_error_codes!(
     CF_NAME => ("CFName", "", ""),
     CODEC => ("Codec", "", ""),
     DATALOSS => ("DataLoss", "", ""),
-    DATACOMPACTED => ("DataCompacted", "", "")
+    DATACOMPACTED => ("DataCompacted", "", ""),
+    CHECKSUM_MISMATCH => ("ChecksumMismatch", "", "")
 );
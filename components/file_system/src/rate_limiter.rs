@@ -105,6 +105,106 @@ impl<'de> Deserialize<'de> for IORateLimitMode {
     }
 }
 
+/// The curve mapping normalized compaction pending bytes to the fraction of
+/// total IO budgets granted to rate limited IO.
+///
+/// All curves map a score of 0 to half of the budgets and a score of 1 (the
+/// soft limit) to the full budgets; they differ in how aggressively budgets
+/// grow in between. A concave curve (`Sqrt`) hands out budgets early and keeps
+/// pending bytes low, a convex one (`Quadratic`) holds budgets back until the
+/// backlog is close to the soft limit.
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum IOBudgetCurve {
+    Sqrt,
+    Linear,
+    Quadratic,
+}
+
+impl IOBudgetCurve {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            IOBudgetCurve::Sqrt => "sqrt",
+            IOBudgetCurve::Linear => "linear",
+            IOBudgetCurve::Quadratic => "quadratic",
+        }
+    }
+
+    /// Maps a normalized pending-bytes score to a budget fraction in
+    /// [0.5, 1.0].
+    pub fn transform(&self, score: f32) -> f32 {
+        self.transform_with_floor(score, 0.5)
+    }
+
+    /// Like `transform` but with a custom lower bound: the result slides
+    /// between `floor` and 1.0. A higher floor guarantees compaction more IO
+    /// on devices with high write amplification.
+    pub fn transform_with_floor(&self, score: f32, floor: f32) -> f32 {
+        let score = match *self {
+            IOBudgetCurve::Sqrt => score.sqrt(),
+            IOBudgetCurve::Linear => score,
+            IOBudgetCurve::Quadratic => score * score,
+        };
+        // The target flow slides between Bandwidth * floor and Bandwidth.
+        floor + score.min(1.0) * (1.0 - floor)
+    }
+}
+
+impl FromStr for IOBudgetCurve {
+    type Err = String;
+    fn from_str(s: &str) -> Result<IOBudgetCurve, String> {
+        match s {
+            "sqrt" => Ok(IOBudgetCurve::Sqrt),
+            "linear" => Ok(IOBudgetCurve::Linear),
+            "quadratic" => Ok(IOBudgetCurve::Quadratic),
+            s => Err(format!("expect: sqrt, linear or quadratic, got: {:?}", s)),
+        }
+    }
+}
+
+impl Serialize for IOBudgetCurve {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for IOBudgetCurve {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::{Error, Unexpected, Visitor};
+        struct StrVistor;
+        impl<'de> Visitor<'de> for StrVistor {
+            type Value = IOBudgetCurve;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(formatter, "a IO budget curve")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<IOBudgetCurve, E>
+            where
+                E: Error,
+            {
+                let p = match IOBudgetCurve::from_str(&*value.trim().to_lowercase()) {
+                    Ok(p) => p,
+                    _ => {
+                        return Err(E::invalid_value(
+                            Unexpected::Other(&"invalid IO budget curve".to_string()),
+                            &self,
+                        ));
+                    }
+                };
+                Ok(p)
+            }
+        }
+
+        deserializer.deserialize_str(StrVistor)
+    }
+}
+
 /// Record accumulated bytes through of different types.
 /// Used for testing and metrics.
 #[derive(Debug)]
@@ -606,6 +706,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_budget_curve_transform() {
+        // All curves share the endpoints: empty backlog halves the budgets,
+        // a backlog at (or past) the soft limit grants them all.
+        for curve in &[
+            IOBudgetCurve::Sqrt,
+            IOBudgetCurve::Linear,
+            IOBudgetCurve::Quadratic,
+        ] {
+            assert!((curve.transform(0.0) - 0.5).abs() < f32::EPSILON);
+            assert!((curve.transform(1.0) - 1.0).abs() < f32::EPSILON);
+            assert!((curve.transform(4.0) - 1.0).abs() < f32::EPSILON);
+        }
+        // A quarter of the soft limit maps according to the curve shape.
+        assert!((IOBudgetCurve::Sqrt.transform(0.25) - 0.75).abs() < f32::EPSILON);
+        assert!((IOBudgetCurve::Linear.transform(0.25) - 0.625).abs() < f32::EPSILON);
+        assert!((IOBudgetCurve::Quadratic.transform(0.25) - 0.53125).abs() < f32::EPSILON);
+    }
+
     #[test]
     fn test_rate_limit_toggle() {
         let bytes_per_sec = 2000;
@@ -822,4 +941,28 @@ mod tests {
             inner_limiter.critical_section(now);
         });
     }
+
+    #[test]
+    fn test_budget_curve_floor() {
+        for curve in &[
+            IOBudgetCurve::Sqrt,
+            IOBudgetCurve::Linear,
+            IOBudgetCurve::Quadratic,
+        ] {
+            for floor in &[0.2f32, 0.5, 0.8] {
+                // The budget fraction must stay within [floor, 1.0] over the
+                // whole score range, including scores above the soft limit.
+                for i in 0..=20 {
+                    let score = i as f32 / 10.0;
+                    let fraction = curve.transform_with_floor(score, *floor);
+                    assert!(fraction >= *floor, "{:?} {} {}", curve, score, fraction);
+                    assert!(fraction <= 1.0, "{:?} {} {}", curve, score, fraction);
+                }
+                assert_eq!(curve.transform_with_floor(0.0, *floor), *floor);
+                assert_eq!(curve.transform_with_floor(1.0, *floor), 1.0);
+            }
+            // The default floor preserves the historical behavior.
+            assert_eq!(curve.transform(0.0), 0.5);
+        }
+    }
 }
@@ -4,9 +4,10 @@ use std::fs;
 use std::path::Path;
 
 use engine_traits::{
-    CacheStats, RaftEngine, RaftEngineReadOnly, RaftLogBatch as RaftLogBatchTrait, Result,
+    CacheStats, Error, RaftEngine, RaftEngineDebug, RaftEngineReadOnly,
+    RaftLogBatch as RaftLogBatchTrait, Result, WalStats,
 };
-use kvproto::raft_serverpb::RaftLocalState;
+use kvproto::raft_serverpb::{RaftApplyState, RaftLocalState};
 use raft::eraftpb::Entry;
 use raft_engine::{EntryExt, Error as RaftEngineError, LogBatch, RaftLogEngine as RawRaftEngine};
 
@@ -91,6 +92,20 @@ impl RaftEngineReadOnly for RaftLogEngine {
             .map_err(transfer_error)
     }
 
+    fn first_index(&self, raft_group_id: u64) -> Result<Option<u64>> {
+        Ok(self.0.first_index(raft_group_id))
+    }
+
+    fn region_log_span(&self, raft_group_id: u64) -> Result<Option<(u64, u64)>> {
+        match (
+            self.0.first_index(raft_group_id),
+            self.0.last_index(raft_group_id),
+        ) {
+            (Some(first), Some(last)) => Ok(Some((first, last))),
+            _ => Ok(None),
+        }
+    }
+
     fn fetch_entries_to(
         &self,
         raft_group_id: u64,
@@ -190,12 +205,112 @@ impl RaftEngine for RaftLogEngine {
         Ok("".to_owned())
     }
 
+    fn wal_stats(&self) -> Result<WalStats> {
+        // TODO: report real numbers when RaftLogEngine is ready to go online.
+        Ok(WalStats::default())
+    }
+
     fn get_engine_size(&self) -> Result<u64> {
         //TODO impl this when RaftLogEngine is ready to go online.
         Ok(0)
     }
 }
 
+impl RaftEngineDebug for RaftLogEngine {
+    fn find_log_holes(&self, raft_group_id: u64) -> Result<Vec<(u64, u64)>> {
+        let (first, last) = match (
+            self.first_index(raft_group_id),
+            self.last_index(raft_group_id),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        let mut holes = Vec::new();
+        let mut hole_start = None;
+        for index in first..=last {
+            if self.get_entry(raft_group_id, index)?.is_some() {
+                if let Some(start) = hole_start.take() {
+                    holes.push((start, index - 1));
+                }
+            } else if hole_start.is_none() {
+                hole_start = Some(index);
+            }
+        }
+        Ok(holes)
+    }
+
+    fn check_region_consistency(
+        &self,
+        raft_group_id: u64,
+        apply_state: &RaftApplyState,
+    ) -> Result<()> {
+        let raft_state = self.get_raft_state(raft_group_id)?.ok_or_else(|| {
+            Error::Other(box_err!("[region {}] raft state is missing", raft_group_id))
+        })?;
+        let last_index = raft_state.get_last_index();
+        let truncated_index = apply_state.get_truncated_state().get_index();
+        if last_index < truncated_index {
+            return Err(box_err!(
+                "[region {}] last index {} is smaller than truncated index {}",
+                raft_group_id,
+                last_index,
+                truncated_index
+            ));
+        }
+        let applied_index = apply_state.get_applied_index();
+        if applied_index > last_index {
+            return Err(box_err!(
+                "[region {}] applied index {} exceeds last index {}",
+                raft_group_id,
+                applied_index,
+                last_index
+            ));
+        }
+        // Entries in (truncated_index, last_index] must be stored contiguously.
+        for index in truncated_index + 1..=last_index {
+            if self.get_entry(raft_group_id, index)?.is_none() {
+                return Err(box_err!(
+                    "[region {}] log entry {} is missing",
+                    raft_group_id,
+                    index
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn rewrite_region(&self, raft_group_id: u64) -> Result<()> {
+        // Re-append the live log so it lands in the active log file. The
+        // stale copies spread across older files stop pinning them and are
+        // dropped by the next `purge_expired_files`, which also reports the
+        // reclaimed bytes.
+        const REWRITE_BATCH_INDEXES: u64 = 1024;
+
+        let (first, last) = match (
+            self.first_index(raft_group_id),
+            self.last_index(raft_group_id),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(()),
+        };
+        let mut low = first;
+        while low <= last {
+            let high = std::cmp::min(low + REWRITE_BATCH_INDEXES, last + 1);
+            let mut entries = Vec::with_capacity((high - low) as usize);
+            self.fetch_entries_to(raft_group_id, low, high, None, &mut entries)?;
+            let mut batch = LogBatch::default();
+            batch.add_entries(raft_group_id, entries);
+            box_try!(self.0.write(&mut batch, false));
+            low = high;
+        }
+        if let Some(state) = self.get_raft_state(raft_group_id)? {
+            self.put_raft_state(raft_group_id, &state)?;
+        }
+        box_try!(self.0.sync());
+        Ok(())
+    }
+}
+
 fn transfer_error(e: RaftEngineError) -> engine_traits::Error {
     match e {
         RaftEngineError::StorageCompacted => engine_traits::Error::EntriesCompacted,
@@ -9,6 +9,7 @@ use crate::{coprocessor, Result};
 use batch_system::Config as BatchSystemConfig;
 use engine_traits::config as engine_config;
 use engine_traits::PerfLevel;
+use engine_traits::ALL_CFS;
 use lazy_static::lazy_static;
 use online_config::{ConfigChange, ConfigManager, ConfigValue, OnlineConfig};
 use prometheus::register_gauge_vec;
@@ -190,6 +191,11 @@ pub struct Config {
     #[serde(with = "engine_config::perf_level_serde")]
     #[online_config(skip)]
     pub perf_level: PerfLevel,
+
+    // Column families that are still allowed to be written when the disk is
+    // already full. Empty by default, which rejects all normal writes.
+    #[online_config(skip)]
+    pub disk_full_allowed_cfs: Vec<String>,
 }
 
 impl Default for Config {
@@ -260,6 +266,7 @@ impl Default for Config {
             region_split_size: ReadableSize(0),
             clean_stale_peer_delay: ReadableDuration::minutes(0),
             perf_level: PerfLevel::Disable,
+            disk_full_allowed_cfs: vec![],
         }
     }
 }
@@ -434,6 +441,15 @@ impl Config {
             return Err(box_err!("future-poll-size should be greater than 0."));
         }
 
+        for cf in &self.disk_full_allowed_cfs {
+            if !ALL_CFS.contains(&cf.as_str()) {
+                return Err(box_err!(
+                    "disk-full-allowed-cfs contains unknown cf {}",
+                    cf
+                ));
+            }
+        }
+
         // Avoid hibernated peer being reported as down peer.
         if self.hibernate_regions {
             self.max_peer_down_duration = std::cmp::max(
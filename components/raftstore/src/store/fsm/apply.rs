@@ -1338,6 +1338,26 @@ where
     }
 }
 
+/// Returns `(region_id, after_index)` of an injected apply error for the
+/// store, if any. Tests configure the `apply_injected_error_{store_id}`
+/// failpoint with `return(region_id:after_index)` as payload, see
+/// `Cluster::inject_apply_error`.
+fn injected_apply_error(store_id: u64) -> Option<(u64, u64)> {
+    fn parse(payload: Option<String>) -> Option<(u64, u64)> {
+        let payload = payload?;
+        let mut parts = payload.split(':');
+        let region_id = parts.next()?.parse().ok()?;
+        let after_index = parts.next()?.parse().ok()?;
+        Some((region_id, after_index))
+    }
+    fail_point!("apply_injected_error_1", store_id == 1, parse);
+    fail_point!("apply_injected_error_2", store_id == 2, parse);
+    fail_point!("apply_injected_error_3", store_id == 3, parse);
+    fail_point!("apply_injected_error_4", store_id == 4, parse);
+    fail_point!("apply_injected_error_5", store_id == 5, parse);
+    None
+}
+
 impl<EK> ApplyDelegate<EK>
 where
     EK: KvEngine,
@@ -1348,6 +1368,12 @@ where
         ctx: &mut ApplyContext<EK, W>,
         req: &RaftCmdRequest,
     ) -> Result<(RaftCmdResponse, ApplyResult<EK::Snapshot>)> {
+        if let Some((region_id, after_index)) = injected_apply_error(ctx.store_id) {
+            let index = ctx.exec_ctx.as_ref().unwrap().index;
+            if self.region_id() == region_id && index > after_index {
+                return Err(box_err!("injected apply error at index {}", index));
+            }
+        }
         // Include region for epoch not match after merge may cause key not in range.
         let include_region =
             req.get_header().get_region_epoch().get_version() >= self.last_merge_version;
@@ -3590,6 +3616,8 @@ where
 
 pub struct ControlMsg;
 
+impl HeapSize for ControlMsg {}
+
 pub struct ControlFsm;
 
 impl Fsm for ControlFsm {
@@ -3675,7 +3703,10 @@ where
         );
         while self.msg_buf.len() < self.messages_per_tick {
             match normal.receiver.try_recv() {
-                Ok(msg) => self.msg_buf.push(msg),
+                Ok(msg) => {
+                    batch_system::message_dequeued(&msg);
+                    self.msg_buf.push(msg)
+                }
                 Err(TryRecvError::Empty) => {
                     expected_msg_count = Some(0);
                     break;
@@ -151,6 +151,7 @@ where
         self.peer.stop();
         let mut raft_messages_size = 0;
         while let Ok(msg) = self.receiver.try_recv() {
+            batch_system::message_dequeued(&msg);
             let callback = match msg {
                 PeerMsg::RaftCommand(cmd) => cmd.callback,
                 PeerMsg::CasualMessage(CasualMessage::SplitRegion { callback, .. }) => callback,
@@ -677,6 +678,11 @@ where
             CasualMessage::ForceCompactRaftLogs => {
                 self.on_raft_gc_log_tick(true);
             }
+            CasualMessage::ForceRegionHeartbeat => {
+                if self.fsm.peer.is_leader() {
+                    self.fsm.peer.heartbeat_pd(self.ctx);
+                }
+            }
             CasualMessage::AccessPeer(cb) => cb(self.fsm as &mut dyn AbstractPeer),
             CasualMessage::QueryRegionLeaderResp { region, leader } => {
                 // the leader already updated
@@ -807,7 +807,10 @@ impl<EK: KvEngine, ER: RaftEngine, T: Transport> PollHandler<PeerFsm<EK, ER>, St
         let mut expected_msg_count = None;
         while self.store_msg_buf.len() < self.messages_per_tick {
             match store.receiver.try_recv() {
-                Ok(msg) => self.store_msg_buf.push(msg),
+                Ok(msg) => {
+                    batch_system::message_dequeued(&msg);
+                    self.store_msg_buf.push(msg)
+                }
                 Err(TryRecvError::Empty) => {
                     expected_msg_count = Some(0);
                     break;
@@ -857,6 +860,7 @@ impl<EK: KvEngine, ER: RaftEngine, T: Transport> PollHandler<PeerFsm<EK, ER>, St
                             ),
                         |_| unreachable!()
                     );
+                    batch_system::message_dequeued(&msg);
                     self.peer_msg_buf.push(msg);
                 }
                 Err(TryRecvError::Empty) => {
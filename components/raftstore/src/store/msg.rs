@@ -337,6 +337,10 @@ pub enum CasualMessage<EK: KvEngine> {
     /// however `ForceCompactRaftLogs` only cares the leader itself.
     ForceCompactRaftLogs,
 
+    /// Reports a region heartbeat to PD right away instead of waiting for
+    /// the heartbeat tick. Only used by tests.
+    ForceRegionHeartbeat,
+
     /// A message to access peer's internal state.
     AccessPeer(Box<dyn FnOnce(&mut dyn AbstractPeer) + Send + 'static>),
 
@@ -395,6 +399,7 @@ impl<EK: KvEngine> fmt::Debug for CasualMessage<EK> {
             CasualMessage::RegionOverlapped => write!(fmt, "RegionOverlapped"),
             CasualMessage::SnapshotGenerated => write!(fmt, "SnapshotGenerated"),
             CasualMessage::ForceCompactRaftLogs => write!(fmt, "ForceCompactRaftLogs"),
+            CasualMessage::ForceRegionHeartbeat => write!(fmt, "ForceRegionHeartbeat"),
             CasualMessage::AccessPeer(_) => write!(fmt, "AccessPeer"),
             CasualMessage::QueryRegionLeaderResp { .. } => write!(fmt, "QueryRegionLeaderResp"),
         }
@@ -461,6 +466,16 @@ pub enum PeerMsg<EK: KvEngine> {
     Destroy(u64),
 }
 
+impl<EK: KvEngine> HeapSize for PeerMsg<EK> {
+    fn heap_size(&self) -> usize {
+        match self {
+            PeerMsg::RaftMessage(im) => im.heap_size,
+            PeerMsg::RaftCommand(cmd) => cmd.request.heap_size(),
+            _ => 0,
+        }
+    }
+}
+
 impl<EK: KvEngine> fmt::Debug for PeerMsg<EK> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -516,6 +531,18 @@ where
     UpdateReplicationMode(ReplicationStatus),
 }
 
+impl<EK> HeapSize for StoreMsg<EK>
+where
+    EK: KvEngine,
+{
+    fn heap_size(&self) -> usize {
+        match self {
+            StoreMsg::RaftMessage(im) => im.heap_size,
+            _ => 0,
+        }
+    }
+}
+
 impl<EK> fmt::Debug for StoreMsg<EK>
 where
     EK: KvEngine,
@@ -46,7 +46,9 @@ use crate::store::fsm::{apply, Apply, ApplyMetrics, ApplyTask, CollectedReady, P
 use crate::store::hibernate_state::GroupState;
 use crate::store::memory::{needs_evict_entry_cache, MEMTRACE_RAFT_ENTRIES};
 use crate::store::msg::RaftCommand;
-use crate::store::util::{admin_cmd_epoch_lookup, RegionReadProgress};
+#[cfg(any(test, feature = "testexport"))]
+use crate::store::util::read_path;
+use crate::store::util::{admin_cmd_epoch_lookup, lease_clock_now, RegionReadProgress};
 use crate::store::worker::{
     HeartbeatTask, QueryStats, ReadDelegate, ReadExecutor, ReadProgress, RegionTask,
 };
@@ -59,7 +61,7 @@ use pd_client::INVALID_ID;
 use tikv_alloc::trace::TraceEvent;
 use tikv_util::codec::number::decode_u64;
 use tikv_util::sys::disk;
-use tikv_util::time::{duration_to_sec, monotonic_raw_now};
+use tikv_util::time::duration_to_sec;
 use tikv_util::time::{Instant as TiInstant, InstantExt, ThreadReadId};
 use tikv_util::worker::{FutureScheduler, Scheduler};
 use tikv_util::Either;
@@ -1093,7 +1095,7 @@ where
                     // network partition from the new leader.
                     // For lease safety during leader transfer, transit `leader_lease`
                     // to suspect.
-                    self.leader_lease.suspect(monotonic_raw_now());
+                    self.leader_lease.suspect(lease_clock_now(self.peer.get_store_id()));
 
                     metrics.timeout_now[i] += 1;
                 }
@@ -1390,7 +1392,11 @@ where
                     // this peer becomes leader because it's more convenient to do it here and
                     // it has no impact on the correctness.
                     let progress_term = ReadProgress::term(self.term());
-                    self.maybe_renew_leader_lease(monotonic_raw_now(), ctx, Some(progress_term));
+                    self.maybe_renew_leader_lease(
+                        lease_clock_now(self.peer.get_store_id()),
+                        ctx,
+                        Some(progress_term),
+                    );
                     debug!(
                         "becomes leader with lease";
                         "region_id" => self.region_id,
@@ -1484,7 +1490,7 @@ where
                     // merges majority of this region, also it can not know when the target
                     // region writes new values.
                     // To prevent unsafe local read, we suspect its leader lease.
-                    self.leader_lease.suspect(monotonic_raw_now());
+                    self.leader_lease.suspect(lease_clock_now(self.peer.get_store_id()));
                     // Stop updating `safe_ts`
                     self.read_progress.discard();
                 }
@@ -1853,7 +1859,7 @@ where
             // If we do not renew it, this time may be smaller than propose_time of a command,
             // which was proposed in another thread while this thread receives its AppendEntriesResponse
             // and is ready to calculate its commit-log-duration.
-            ctx.current_time.replace(monotonic_raw_now());
+            ctx.current_time.replace(lease_clock_now(self.peer.get_store_id()));
         }
         // Leader needs to update lease.
         let mut lease_to_be_updated = self.is_leader();
@@ -2333,7 +2339,9 @@ where
             Ok(RequestPolicy::ReadIndex) => return self.read_index(ctx, req, err_resp, cb),
             Ok(RequestPolicy::ProposeNormal) => {
                 let store_id = ctx.store_id();
-                if disk::disk_full_precheck(store_id) || ctx.is_disk_full {
+                if (disk::disk_full_precheck(store_id) || ctx.is_disk_full)
+                    && !is_write_to_allowed_cfs(&req, &ctx.cfg.disk_full_allowed_cfs)
+                {
                     Err(Error::Timeout("disk full".to_owned()))
                 } else {
                     self.propose_normal(ctx, req)
@@ -2399,7 +2407,7 @@ where
     ) {
         // Try to renew leader lease on every consistent read/write request.
         if poll_ctx.current_time.is_none() {
-            poll_ctx.current_time = Some(monotonic_raw_now());
+            poll_ctx.current_time = Some(lease_clock_now(poll_ctx.store_id()));
         }
         p.propose_time = poll_ctx.current_time;
 
@@ -2621,6 +2629,8 @@ where
         cb: Callback<EK::Snapshot>,
     ) {
         ctx.raft_metrics.propose.local_read += 1;
+        #[cfg(any(test, feature = "testexport"))]
+        read_path::record_lease_read(self.peer.get_store_id());
         cb.invoke_read(self.handle_read(ctx, req, false, Some(self.get_store().commit_index())))
     }
 
@@ -2709,7 +2719,10 @@ where
             return false;
         }
 
-        let now = monotonic_raw_now();
+        #[cfg(any(test, feature = "testexport"))]
+        read_path::record_read_index(self.peer.get_store_id());
+
+        let now = lease_clock_now(self.peer.get_store_id());
         if self.is_leader() {
             match self.inspect_lease() {
                 // Here combine the new read request with the previous one even if the lease expired is
@@ -3840,6 +3853,26 @@ fn is_request_urgent(req: &RaftCmdRequest) -> bool {
     )
 }
 
+/// Checks whether every write in the request only touches column families
+/// that are still allowed to be written when the disk is already full.
+/// Requests carrying no allowed writes keep the default rejection behavior.
+fn is_write_to_allowed_cfs(req: &RaftCmdRequest, allowed_cfs: &[String]) -> bool {
+    if allowed_cfs.is_empty() || req.has_admin_request() || req.get_requests().is_empty() {
+        return false;
+    }
+    req.get_requests().iter().all(|r| {
+        let cf = match r.get_cmd_type() {
+            CmdType::Put => r.get_put().get_cf(),
+            CmdType::Delete => r.get_delete().get_cf(),
+            CmdType::DeleteRange => r.get_delete_range().get_cf(),
+            // Other command types (e.g. IngestSst) can't be checked against
+            // a column family, so they never bypass the rejection.
+            _ => return false,
+        };
+        allowed_cfs.iter().any(|allowed| allowed == cf)
+    })
+}
+
 fn make_transfer_leader_response() -> RaftCmdResponse {
     let mut response = AdminResponse::default();
     response.set_cmd_type(AdminCmdType::TransferLeader);
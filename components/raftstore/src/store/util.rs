@@ -11,6 +11,7 @@ use kvproto::kvrpcpb::{self, KeyRange, LeaderInfo};
 use kvproto::metapb::{self, Peer, PeerRole, Region, RegionEpoch};
 use kvproto::raft_cmdpb::{AdminCmdType, ChangePeerRequest, ChangePeerV2Request, RaftCmdRequest};
 use kvproto::raft_serverpb::RaftMessage;
+use fail::fail_point;
 use protobuf::{self, Message};
 use raft::eraftpb::{self, ConfChangeType, ConfState, MessageType};
 use raft::INVALID_INDEX;
@@ -605,6 +606,93 @@ impl fmt::Debug for RemoteLease {
     }
 }
 
+/// Returns the current monotonic raw time for lease bookkeeping on the given
+/// store.
+///
+/// Lease related code paths read the clock through this function, so tests
+/// can make a single node's clock jump by configuring the
+/// `lease_clock_offset_{store_id}` failpoint with an offset in milliseconds
+/// as payload, e.g. `return(1000)`. Without an active failpoint it is exactly
+/// `monotonic_raw_now`.
+pub fn lease_clock_now(store_id: u64) -> Timespec {
+    let offset = lease_clock_offset_ms(store_id);
+    if offset == 0 {
+        monotonic_raw_now()
+    } else {
+        monotonic_raw_now() + Duration::milliseconds(offset)
+    }
+}
+
+fn lease_clock_offset_ms(store_id: u64) -> i64 {
+    fail_point!("lease_clock_offset_1", store_id == 1, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("lease_clock_offset_2", store_id == 2, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("lease_clock_offset_3", store_id == 3, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("lease_clock_offset_4", store_id == 4, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("lease_clock_offset_5", store_id == 5, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    0
+}
+
+/// Per-store counters distinguishing reads served by the leader's lease from
+/// reads that went through read-index. They are keyed by store id because
+/// test clusters run several stores in one process, so a process-global
+/// metric couldn't tell them apart; see `Cluster::read_path_counts`. Only
+/// store ids below `MAX_STORES` are tracked, larger ones are ignored.
+///
+/// This is test-only instrumentation on the hottest read path, so it is
+/// compiled out of release builds entirely.
+#[cfg(any(test, feature = "testexport"))]
+pub mod read_path {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const MAX_STORES: usize = 8;
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicU64 = AtomicU64::new(0);
+    static LEASE_READS: [AtomicU64; MAX_STORES] = [ZERO; MAX_STORES];
+    static READ_INDEX_READS: [AtomicU64; MAX_STORES] = [ZERO; MAX_STORES];
+
+    #[inline]
+    pub fn record_lease_read(store_id: u64) {
+        if let Some(c) = LEASE_READS.get(store_id as usize) {
+            c.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub fn record_read_index(store_id: u64) {
+        if let Some(c) = READ_INDEX_READS.get(store_id as usize) {
+            c.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns `(lease_reads, read_index_reads)` of the store since the last
+    /// `reset`.
+    pub fn counts(store_id: u64) -> (u64, u64) {
+        let load = |c: &[AtomicU64]| {
+            c.get(store_id as usize)
+                .map_or(0, |c| c.load(Ordering::Relaxed))
+        };
+        (load(&LEASE_READS), load(&READ_INDEX_READS))
+    }
+
+    pub fn reset(store_id: u64) {
+        for c in &[&LEASE_READS, &READ_INDEX_READS] {
+            if let Some(c) = c.get(store_id as usize) {
+                c.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
 // Contants used in `timespec_to_u64` and `u64_to_timespec`.
 const NSEC_PER_MSEC: i32 = 1_000_000;
 const TIMESPEC_NSEC_SHIFT: usize = 32 - NSEC_PER_MSEC.leading_zeros() as usize;
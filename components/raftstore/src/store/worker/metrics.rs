@@ -117,4 +117,14 @@ lazy_static! {
         "Total number of requests directly executed by local reader."
     )
     .unwrap();
+    pub static ref RAFT_ENGINE_PURGE_COUNTER: IntCounter = register_int_counter!(
+        "tikv_raftstore_raft_engine_purge_total",
+        "Total number of raft engine purge rounds."
+    )
+    .unwrap();
+    pub static ref RAFT_ENGINE_PURGED_BYTES_COUNTER: IntCounter = register_int_counter!(
+        "tikv_raftstore_raft_engine_purged_wal_bytes_total",
+        "Total write-ahead log bytes reclaimed by raft engine purge."
+    )
+    .unwrap();
 }
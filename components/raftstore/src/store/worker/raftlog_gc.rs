@@ -10,8 +10,9 @@ use engine_traits::{Engines, KvEngine, RaftEngine};
 use file_system::{IOType, WithIOType};
 use tikv_util::time::Duration;
 use tikv_util::worker::{Runnable, RunnableWithTimer};
-use tikv_util::{box_try, debug, error, warn};
+use tikv_util::{box_try, debug, error, info, warn};
 
+use super::metrics::{RAFT_ENGINE_PURGED_BYTES_COUNTER, RAFT_ENGINE_PURGE_COUNTER};
 use crate::store::{CasualMessage, CasualRouter};
 
 const MAX_GC_REGION_BATCH: usize = 128;
@@ -119,6 +120,7 @@ impl<EK: KvEngine, ER: RaftEngine, R: CasualRouter<EK>> Runner<EK, ER, R> {
                     }
                 }
                 Task::Purge => {
+                    let before = self.engines.raft.wal_stats().unwrap_or_default();
                     let regions = match self.engines.raft.purge_expired_files() {
                         Ok(regions) => regions,
                         Err(e) => {
@@ -126,6 +128,18 @@ impl<EK: KvEngine, ER: RaftEngine, R: CasualRouter<EK>> Runner<EK, ER, R> {
                             return;
                         }
                     };
+                    RAFT_ENGINE_PURGE_COUNTER.inc();
+                    if let Ok(after) = self.engines.raft.wal_stats() {
+                        RAFT_ENGINE_PURGED_BYTES_COUNTER
+                            .inc_by(before.total_size.saturating_sub(after.total_size));
+                    }
+                    if !regions.is_empty() {
+                        info!(
+                            "raft engine purge demands compaction";
+                            "region_count" => regions.len(),
+                            "regions" => ?regions,
+                        );
+                    }
                     for region_id in regions {
                         let _ = self.ch.send(region_id, CasualMessage::ForceCompactRaftLogs);
                     }
@@ -227,6 +241,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_purge_updates_metrics() {
+        let dir = Builder::new().prefix("purge-metrics-test").tempdir().unwrap();
+        let path_raft = dir.path().join("raft");
+        let path_kv = dir.path().join("kv");
+        let raft_db =
+            engine_test::raft::new_engine(path_raft.to_str().unwrap(), None, CF_DEFAULT, None)
+                .unwrap();
+        let kv_db =
+            engine_test::kv::new_engine(path_kv.to_str().unwrap(), None, ALL_CFS, None).unwrap();
+        let engines = Engines::new(kv_db, raft_db);
+
+        let (tx, _rx) = mpsc::channel();
+        let (r, compact_rx) = mpsc::sync_channel(1);
+        let mut runner = Runner {
+            gc_entries: Some(tx),
+            engines,
+            ch: r,
+            tasks: vec![],
+        };
+
+        let purged = RAFT_ENGINE_PURGE_COUNTER.get();
+        runner.run(Task::Purge);
+        runner.flush();
+        assert_eq!(RAFT_ENGINE_PURGE_COUNTER.get(), purged + 1);
+        // The rocks raft engine has nothing to purge, so no region is asked
+        // to compact.
+        assert!(compact_rx.try_recv().is_err());
+    }
+
     fn raft_log_must_not_exist(
         raft_engine: &impl KvEngine,
         region_id: u64,
@@ -36,6 +36,31 @@ use tikv_util::{debug, error};
 use super::metrics::*;
 use crate::store::fsm::store::StoreMeta;
 
+/// Millisecond delay injected before serving reads on the given store.
+///
+/// Tests can slow down a single store's reads by configuring the
+/// `store_read_delay_{store_id}` failpoint with the delay in milliseconds as
+/// payload, e.g. `return(100)`. It only supports store ids up to 5 and is
+/// zero without an active failpoint. See `Cluster::set_read_delay`.
+fn store_read_delay_ms(store_id: u64) -> u64 {
+    fail_point!("store_read_delay_1", store_id == 1, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("store_read_delay_2", store_id == 2, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("store_read_delay_3", store_id == 3, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("store_read_delay_4", store_id == 4, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    fail_point!("store_read_delay_5", store_id == 5, |p: Option<String>| p
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0));
+    0
+}
+
 pub trait ReadExecutor<E: KvEngine> {
     fn get_engine(&self) -> &E;
     fn get_snapshot(&mut self, ts: Option<ThreadReadId>) -> Arc<E::Snapshot>;
@@ -83,6 +108,10 @@ pub trait ReadExecutor<E: KvEngine> {
         read_index: Option<u64>,
         mut ts: Option<ThreadReadId>,
     ) -> ReadResponse<E::Snapshot> {
+        let delay = store_read_delay_ms(msg.get_header().get_peer().get_store_id());
+        if delay > 0 {
+            std::thread::sleep(Duration::from_millis(delay));
+        }
         let requests = msg.get_requests();
         let mut response = ReadResponse {
             response: RaftCmdResponse::default(),
@@ -549,22 +578,26 @@ where
                 let mut response = match policy {
                     // Leader can read local if and only if it is in lease.
                     RequestPolicy::ReadLocal => {
+                        // `store_id` has been filled by `pre_propose_raft_command`.
+                        let store_id = self.store_id.get().unwrap();
                         let snapshot_ts = match read_id.as_mut() {
                             // If this peer became Leader not long ago and just after the cached
                             // snapshot was created, this snapshot can not see all data of the peer.
                             Some(id) => {
                                 if id.create_time <= delegate.last_valid_ts {
-                                    id.create_time = monotonic_raw_now();
+                                    id.create_time = util::lease_clock_now(store_id);
                                 }
                                 id.create_time
                             }
-                            None => monotonic_raw_now(),
+                            None => util::lease_clock_now(store_id),
                         };
                         if !delegate.is_in_leader_lease(snapshot_ts, &mut self.metrics) {
                             // Forward to raftstore.
                             self.redirect(RaftCommand::new(req, cb));
                             return;
                         }
+                        #[cfg(any(test, feature = "testexport"))]
+                        util::read_path::record_lease_read(store_id);
                         self.execute(&req, &delegate.region, None, read_id)
                     }
                     // Replica can serve stale read if and only if its `safe_ts` >= `read_ts`
@@ -69,6 +69,102 @@ fn clear_raft_db(engine: &RocksEngine) -> Result<(), EngineError> {
     Ok(())
 }
 
+/// Estimated work of a pending migration between raftdb and raft-log-engine.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MigrationEstimate {
+    /// Raft groups that will be dumped.
+    pub regions: u64,
+    /// Approximate bytes of raft states and log entries to be dumped.
+    pub bytes: u64,
+}
+
+/// Estimates the work `check_and_dump_raft_db` or `check_and_dump_raft_engine`
+/// would perform under `config`, without moving any data. The direction is
+/// decided by `raft-engine.enable` just like on startup. Returns `None` when
+/// no migration is pending, i.e. the source engine doesn't exist. Useful to
+/// gauge the restart downtime before flipping `raft-engine.enable`.
+pub fn estimate_migration(config: &TiKvConfig, env: &Arc<Env>) -> Option<MigrationEstimate> {
+    if config.raft_engine.enable {
+        estimate_migration_from_raft_db(config, env)
+    } else {
+        estimate_migration_from_raft_engine(config)
+    }
+}
+
+fn estimate_migration_from_raft_db(config: &TiKvConfig, env: &Arc<Env>) -> Option<MigrationEstimate> {
+    let raftdb_path = &config.raft_store.raftdb_path;
+    if !RocksEngine::exists(raftdb_path) {
+        return None;
+    }
+
+    let config_raftdb = &config.raftdb;
+    let mut raft_db_opts = config_raftdb.build_opt();
+    raft_db_opts.set_env(env.clone());
+    let raft_db_cf_opts = config_raftdb.build_cf_opts(&None);
+    let db = engine_rocks::raw_util::new_engine_opt(raftdb_path, raft_db_opts, raft_db_cf_opts)
+        .unwrap_or_else(|s| fatal!("failed to open origin raft db: {}", s));
+    let src_engine = RocksEngine::from_db(Arc::new(db));
+
+    let mut estimate = MigrationEstimate::default();
+    // Enumerate region ids the same way the dump does, but only sum up the
+    // value sizes instead of writing anything out.
+    let mut it = src_engine.iterator().unwrap();
+    let mut valid = it.seek(SeekKey::Key(keys::REGION_RAFT_MIN_KEY)).unwrap();
+    while valid {
+        match keys::decode_raft_key(it.key()) {
+            Err(e) => {
+                panic!("Error happened when decoding raft key: {}", e);
+            }
+            Ok((id, _)) => {
+                estimate.regions += 1;
+                src_engine
+                    .scan(
+                        &keys::raft_log_prefix(id),
+                        &keys::raft_log_prefix(id + 1),
+                        false,
+                        |_, value| {
+                            estimate.bytes += value.len() as u64;
+                            Ok(true)
+                        },
+                    )
+                    .unwrap();
+                let next_key = keys::raft_log_prefix(id + 1);
+                valid = it.seek(SeekKey::Key(&next_key)).unwrap();
+            }
+        }
+    }
+    Some(estimate)
+}
+
+fn estimate_migration_from_raft_engine(config: &TiKvConfig) -> Option<MigrationEstimate> {
+    let raft_engine_config = config.raft_engine.config();
+    if !RaftLogEngine::exists(&raft_engine_config.dir) {
+        return None;
+    }
+    let src_engine = RaftLogEngine::new(raft_engine_config);
+
+    let mut estimate = MigrationEstimate::default();
+    for id in src_engine.raft_groups() {
+        estimate.regions += 1;
+        let state = src_engine.get_raft_state(id).unwrap().unwrap();
+        estimate.bytes += state.compute_size() as u64;
+        if let Some(last_index) = src_engine.last_index(id) {
+            let mut begin = src_engine.first_index(id).unwrap();
+            while begin <= last_index {
+                let end = cmp::min(begin + 1024, last_index + 1);
+                let mut entries = Vec::with_capacity((end - begin) as usize);
+                begin += src_engine
+                    .fetch_entries_to(id, begin, end, Some(BATCH_THRESHOLD), &mut entries)
+                    .unwrap() as u64;
+                for e in &entries {
+                    estimate.bytes += u64::from(e.compute_size());
+                }
+            }
+        }
+    }
+    Some(estimate)
+}
+
 /// Check the potential original raftdb directory and try to dump data out.
 ///
 /// Procedure:
@@ -99,7 +195,9 @@ pub fn check_and_dump_raft_db(
     let raft_db_cf_opts = config_raftdb.build_cf_opts(&None);
     let db = engine_rocks::raw_util::new_engine_opt(&raftdb_path, raft_db_opts, raft_db_cf_opts)
         .unwrap_or_else(|s| fatal!("failed to create origin raft db: {}", s));
-    let src_engine = RocksEngine::from_db(Arc::new(db));
+    let mut src_engine = RocksEngine::from_db(Arc::new(db));
+    // The source values may carry entry checksums; decode accordingly.
+    src_engine.set_raft_log_checksum(config_raftdb.enable_log_checksum);
 
     let count_size = Arc::new(AtomicUsize::new(0));
     let mut count_region = 0;
@@ -175,9 +273,7 @@ fn run_dump_raftdb_worker(
                             local_size += value.len();
                             match suffix {
                                 keys::RAFT_LOG_SUFFIX => {
-                                    let mut entry = Entry::default();
-                                    entry.merge_from_bytes(&value)?;
-                                    entries.push(entry);
+                                    entries.push(old_engine.decode_raft_log_value(value)?);
                                 }
                                 keys::RAFT_STATE_SUFFIX => {
                                     let mut state = RaftLocalState::default();
@@ -386,6 +482,60 @@ mod tests {
         do_test_switch(false, true);
     }
 
+    #[test]
+    fn test_estimate_migration() {
+        let data_path = tempfile::Builder::new().tempdir().unwrap().into_path();
+        let mut raftdb_path = data_path.clone();
+        let mut raft_engine_path = data_path;
+        raftdb_path.push("raft");
+        raft_engine_path.push("raft-engine");
+
+        let mut cfg = TiKvConfig::default();
+        cfg.raft_store.raftdb_path = raftdb_path.to_str().unwrap().to_owned();
+        cfg.raft_engine.mut_config().dir = raft_engine_path.to_str().unwrap().to_owned();
+        cfg.raft_engine.enable = true;
+
+        let env = Arc::new(Env::default());
+        // No source engine yet, so no migration is pending.
+        assert_eq!(estimate_migration(&cfg, &env), None);
+
+        // Prepare some data for the RocksEngine.
+        {
+            let db = engine_rocks::raw_util::new_engine_opt(
+                &cfg.raft_store.raftdb_path,
+                cfg.raftdb.build_opt(),
+                cfg.raftdb.build_cf_opts(&None),
+            )
+            .unwrap();
+            let engine = RocksEngine::from_db(Arc::new(db));
+            let mut batch = engine.log_batch(0);
+            set_write_batch(1, &mut batch);
+            set_write_batch(5, &mut batch);
+            set_write_batch(15, &mut batch);
+            engine.consume(&mut batch, false).unwrap();
+            engine.sync().unwrap();
+        }
+
+        let estimate = estimate_migration(&cfg, &env).unwrap();
+        assert_eq!(estimate.regions, 3);
+        assert!(estimate.bytes > 0);
+
+        // The dump must cover exactly the estimated regions.
+        let raft_engine = RaftLogEngine::new(cfg.raft_engine.config());
+        check_and_dump_raft_db(&cfg, &raft_engine, &env, 4);
+        assert_eq!(raft_engine.raft_groups().len() as u64, estimate.regions);
+        drop(raft_engine);
+
+        // The raftdb is gone now, so the reverse direction reports the dumped
+        // regions while the forward one reports nothing.
+        cfg.raft_engine.enable = false;
+        let estimate = estimate_migration(&cfg, &env).unwrap();
+        assert_eq!(estimate.regions, 3);
+        assert!(estimate.bytes > 0);
+        cfg.raft_engine.enable = true;
+        assert_eq!(estimate_migration(&cfg, &env), None);
+    }
+
     // Insert some data into log batch.
     fn set_write_batch<T: RaftLogBatch>(num: u64, batch: &mut T) {
         let mut state = RaftLocalState::default();
@@ -18,7 +18,7 @@ use std::{
     net::SocketAddr,
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicU32, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
         Arc, Mutex,
     },
     time::Duration,
@@ -38,7 +38,7 @@ use engine_traits::{
 };
 use error_code::ErrorCodeExt;
 use file_system::{
-    set_io_rate_limiter, BytesFetcher, IOBudgetAdjustor, IORateLimiter,
+    set_io_rate_limiter, BytesFetcher, IOBudgetAdjustor, IOBudgetCurve, IORateLimiter,
     MetricsManager as IOMetricsManager,
 };
 use fs2::FileExt;
@@ -84,7 +84,12 @@ use tikv::{
         ttl::TTLChecker,
         Node, RaftKv, Server, CPU_CORES_QUOTA_GAUGE, DEFAULT_CLUSTER_ID, GRPC_THREAD_PREFIX,
     },
-    storage::{self, config::StorageConfigManger, mvcc::MvccConsistencyCheckObserver, Engine},
+    storage::{
+        self,
+        config::{self as storage_config, StorageConfigManger},
+        mvcc::MvccConsistencyCheckObserver,
+        Engine,
+    },
 };
 use tikv_util::{
     check_environment_variables,
@@ -248,6 +253,12 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         ));
         let region_info_accessor = RegionInfoAccessor::new(coprocessor_host.as_mut().unwrap());
 
+        check_clock_drift(
+            &*pd_client,
+            config.server.clock_drift_threshold.0,
+            config.server.panic_on_clock_drift,
+        );
+
         // Initialize concurrency manager
         let latest_ts = block_on(pd_client.get_tso()).expect("failed to get timestamp from PD");
         let concurrency_manager = ConcurrencyManager::new(latest_ts);
@@ -415,20 +426,15 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         if self.config.raft_store.capacity.0 > 0 {
             capacity = cmp::min(capacity, self.config.raft_store.capacity.0);
         }
+        // Max one of configured `reserve_space` and `storage.capacity * 5%`.
+        let reserved =
+            storage_config::calculate_reserved_space(capacity, self.config.storage.reserve_space.0);
+        disk::set_disk_reserved_space(reserved);
         //TODO after disk full readonly impl, such file should be removed.
-        file_system::reserve_space_for_recover(
-            &self.config.storage.data_dir,
-            if self.config.storage.reserve_space.0 == 0 {
-                0
-            } else {
-                // Max one of configured `reserve_space` and `storage.capacity * 5%`.
-                cmp::max(
-                    (capacity as f64 * 0.05) as u64,
-                    self.config.storage.reserve_space.0,
-                )
-            },
-        )
-        .unwrap();
+        if !self.config.storage.skip_placeholder_file {
+            file_system::reserve_space_for_recover(&self.config.storage.data_dir, reserved)
+                .unwrap();
+        }
     }
 
     fn init_yatp(&self) {
@@ -542,6 +548,9 @@ impl<ER: RaftEngine> TiKVServer<ER> {
                 self.engines.as_ref().unwrap().engine.kv_engine(),
                 self.config.storage.block_cache.shared,
                 ttl_scheduler,
+                self.config.storage.data_dir.clone(),
+                self.config.raft_store.capacity,
+                self.config.storage.skip_placeholder_file,
             )),
         );
 
@@ -1018,8 +1027,18 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         fetcher: BytesFetcher,
         engines_info: Arc<EnginesResourceInfo>,
     ) {
-        let mut engine_metrics =
-            EngineMetricsManager::new(self.engines.as_ref().unwrap().engines.clone());
+        // The push address is only set when the user explicitly opts in to
+        // pushing, e.g. for short-lived test clusters.
+        let push_gateway = if self.config.metric.address.is_empty() {
+            None
+        } else {
+            Some(self.config.metric.address.clone())
+        };
+        let mut engine_metrics = EngineMetricsManager::new(
+            self.engines.as_ref().unwrap().engines.clone(),
+            push_gateway,
+            self.config.metric.job.clone(),
+        );
         let mut io_metrics = IOMetricsManager::new(fetcher);
         self.background_worker
             .spawn_interval_task(DEFAULT_METRICS_FLUSH_INTERVAL, move || {
@@ -1042,14 +1061,28 @@ impl<ER: RaftEngine> TiKVServer<ER> {
         let config_disk_capacity: u64 = self.config.raft_store.capacity.0;
         let store_path = self.store_path.clone();
         let snap_mgr = self.snap_mgr.clone().unwrap();
-        let disk_reserved = self.config.storage.reserve_space.0;
-        if disk_reserved == 0 {
+        let reserve_inodes = self.config.storage.reserve_inodes;
+        if disk::get_disk_reserved_space() == 0 {
             info!("disk space checker not enabled");
-            return;
+        }
+        // WAL directories may be put on their own devices, in which case they
+        // can fill independently of the data mount and must be watched too.
+        let mut wal_dirs: Vec<String> = Vec::new();
+        for dir in &[&self.config.rocksdb.wal_dir, &self.config.raftdb.wal_dir] {
+            if !dir.is_empty() && !wal_dirs.contains(dir) {
+                wal_dirs.push((*dir).clone());
+            }
         }
         //TODO wal size ignore?
         self.background_worker
             .spawn_interval_task(DEFAULT_STORAGE_STATS_INTERVAL, move || {
+                // Read it every round so that online changes of
+                // `storage.reserve-space` take effect. A zero reservation
+                // only disables the full check: stats must still be
+                // published and a latched disk-full state cleared, or
+                // dropping the reservation while full would reject writes
+                // forever.
+                let disk_reserved = disk::get_disk_reserved_space();
                 let disk_stats = match fs2::statvfs(&store_path) {
                     Err(e) => {
                         error!(
@@ -1083,10 +1116,41 @@ impl<ER: RaftEngine> TiKVServer<ER> {
 
                 let mut available = capacity.checked_sub(used_size).unwrap_or_default();
                 available = cmp::min(available, disk_stats.available_space());
-                if available <= disk_reserved {
+
+                let mut wal_availables = Vec::with_capacity(wal_dirs.len());
+                for dir in &wal_dirs {
+                    match fs2::statvfs(dir) {
+                        Err(e) => {
+                            error!(
+                                "get disk stat for wal dir failed";
+                                "wal path" => %dir,
+                                "err" => ?e
+                            );
+                            return;
+                        }
+                        Ok(stats) => wal_availables.push(stats.available_space()),
+                    }
+                }
+
+                let mut inode_availables = Vec::with_capacity(wal_dirs.len() + 1);
+                if reserve_inodes > 0 {
+                    if let Some(n) = available_inodes(&store_path) {
+                        inode_availables.push(n);
+                    }
+                    for dir in &wal_dirs {
+                        if let Some(n) = available_inodes(dir) {
+                            inode_availables.push(n);
+                        }
+                    }
+                }
+
+                disk::set_disk_stats(capacity, available);
+                if disk_space_exhausted(available, &wal_availables, disk_reserved)
+                    || inodes_exhausted(&inode_availables, reserve_inodes)
+                {
                     warn!(
-                        "disk full, available={},snap={},engine={},capacity={}",
-                        available, snap_size, kv_size, capacity
+                        "disk full, available={},snap={},engine={},capacity={},inodes={:?}",
+                        available, snap_size, kv_size, capacity, inode_availables
                     );
                     disk::set_disk_full();
                 } else if disk::is_disk_full() {
@@ -1202,6 +1266,8 @@ impl TiKVServer<RocksEngine> {
         let shared_block_cache = block_cache.is_some();
         kv_engine.set_shared_block_cache(shared_block_cache);
         raft_engine.set_shared_block_cache(shared_block_cache);
+        raft_engine.set_raft_log_checksum(self.config.raftdb.enable_log_checksum);
+        raft_engine.set_gc_flush_batch_keys(self.config.raftdb.gc_batch_keys);
         let engines = Engines::new(kv_engine, raft_engine);
 
         check_and_dump_raft_engine(&self.config, &engines.raft, 8);
@@ -1227,6 +1293,9 @@ impl TiKVServer<RocksEngine> {
         let engines_info = Arc::new(EnginesResourceInfo::new(
             engines.kv.clone(),
             Some(engines.raft.clone()),
+            self.config.rocksdb.titan.enabled,
+            self.config.storage.io_rate_limit.budget_curve,
+            self.config.storage.io_rate_limit.budget_floor as f32,
             180, /*max_samples_to_preserve*/
         ));
 
@@ -1289,7 +1358,10 @@ impl TiKVServer<RaftLogEngine> {
         let engines_info = Arc::new(EnginesResourceInfo::new(
             engines.kv.clone(),
             None, /*raft_engine*/
-            180,  /*max_samples_to_preserve*/
+            self.config.rocksdb.titan.enabled,
+            self.config.storage.io_rate_limit.budget_curve,
+            self.config.storage.io_rate_limit.budget_floor as f32,
+            180, /*max_samples_to_preserve*/
         ));
 
         (engines, engines_info)
@@ -1325,6 +1397,42 @@ fn pre_start() {
     }
 }
 
+/// Measures the offset between the local wall clock and PD's TSO physical
+/// time and logs it. Leases and TSO ordering assume bounded clock drift, so
+/// an offset above `server.clock-drift-threshold` is reported, fatally when
+/// `server.panic-on-clock-drift` is set. Returns whether the drift was
+/// within the threshold; a failed TSO request skips the check.
+fn check_clock_drift(pd_client: &impl PdClient, threshold: Duration, fatal: bool) -> bool {
+    let tso = match block_on(pd_client.get_tso()) {
+        Ok(tso) => tso,
+        Err(e) => {
+            warn!("skipping clock drift check, failed to get timestamp from PD"; "err" => ?e);
+            return true;
+        }
+    };
+    let local_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64);
+    let drift_ms = (local_ms - tso.physical() as i64).abs() as u64;
+    info!("clock drift against PD measured"; "offset_ms" => drift_ms);
+    if drift_ms > threshold.as_millis() as u64 {
+        if fatal {
+            fatal!(
+                "local clock drifts {}ms from PD, exceeding the {:?} threshold",
+                drift_ms,
+                threshold
+            );
+        }
+        warn!(
+            "local clock drifts from PD beyond the threshold, leases and timestamps may misbehave";
+            "offset_ms" => drift_ms,
+            "threshold" => ?threshold,
+        );
+        return false;
+    }
+    true
+}
+
 fn check_system_config(config: &TiKvConfig) {
     info!("beginning system configuration check");
     let mut rocksdb_max_open_files = config.rocksdb.max_open_files;
@@ -1356,6 +1464,44 @@ fn check_system_config(config: &TiKvConfig) {
             "err" => %e
         );
     }
+
+    // Check that the block cache plus memtables fit into the effective memory
+    // limit. `SysQuota` already folds in cgroup (v1 and v2) limits, so on
+    // containerized deployments this catches configurations that would get
+    // the process OOM killed even though the host has plenty of memory.
+    let memory_limit = SysQuota::memory_limit_in_bytes();
+    let mut memory_budget = config.storage.block_cache.capacity.0.map_or(0, |c| c.0);
+    for (write_buffer_size, write_buffer_number) in &[
+        (
+            config.rocksdb.defaultcf.write_buffer_size,
+            config.rocksdb.defaultcf.max_write_buffer_number,
+        ),
+        (
+            config.rocksdb.writecf.write_buffer_size,
+            config.rocksdb.writecf.max_write_buffer_number,
+        ),
+        (
+            config.rocksdb.lockcf.write_buffer_size,
+            config.rocksdb.lockcf.max_write_buffer_number,
+        ),
+        (
+            config.rocksdb.raftcf.write_buffer_size,
+            config.rocksdb.raftcf.max_write_buffer_number,
+        ),
+        (
+            config.raftdb.defaultcf.write_buffer_size,
+            config.raftdb.defaultcf.max_write_buffer_number,
+        ),
+    ] {
+        memory_budget += write_buffer_size.0 * (*write_buffer_number).max(0) as u64;
+    }
+    if memory_budget > memory_limit {
+        warn!(
+            "check: memory-budget";
+            "block-cache-plus-write-buffers" => memory_budget,
+            "memory-limit" => memory_limit,
+        );
+    }
 }
 
 fn try_lock_conflict_addr<P: AsRef<Path>>(path: P) -> File {
@@ -1386,6 +1532,45 @@ fn get_lock_dir() -> String {
     "TIKV_LOCK_FILES".to_owned()
 }
 
+/// Returns whether the store should be treated as disk full, folding the
+/// availability of WAL mounts into that of the data mount. A WAL directory
+/// sharing the data mount reports at least the data availability, so it
+/// never changes the result; one on its own full device trips the check
+/// even if the data mount still has room.
+fn disk_space_exhausted(available: u64, wal_availables: &[u64], reserved: u64) -> bool {
+    let available = wal_availables.iter().fold(available, |a, w| cmp::min(a, *w));
+    available <= reserved
+}
+
+/// Checks whether any of the monitored mounts is running out of inodes.
+/// Filesystems with many small files can exhaust inodes long before bytes.
+/// A zero `reserved` disables the check, preserving byte-only behavior.
+fn inodes_exhausted(availables: &[u64], reserved: u64) -> bool {
+    reserved > 0 && availables.iter().any(|a| *a <= reserved)
+}
+
+/// Available inodes (`f_favail`) of the mount holding `path`, or `None` if
+/// the platform can't report them.
+#[cfg(unix)]
+fn available_inodes<P: AsRef<Path>>(path: P) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let cpath = CString::new(path.as_ref().as_os_str().as_bytes()).ok()?;
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(cpath.as_ptr(), &mut stat) == 0 {
+            Some(stat.f_favail as u64)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn available_inodes<P: AsRef<Path>>(_path: P) -> Option<u64> {
+    None
+}
+
 /// A small trait for components which can be trivially stopped. Lets us keep
 /// a list of these in `TiKV`, rather than storing each component individually.
 trait Stop {
@@ -1414,15 +1599,41 @@ impl<T: fmt::Display + Send + 'static> Stop for LazyWorker<T> {
     }
 }
 
+lazy_static::lazy_static! {
+    static ref ENGINE_WAL_SIZE_GAUGE: prometheus::IntGaugeVec = prometheus::register_int_gauge_vec!(
+        "tikv_engine_wal_size_bytes",
+        "Total size of engine WAL files.",
+        &["db"]
+    )
+    .unwrap();
+    static ref ENGINE_WAL_RECYCLED_FILES_GAUGE: prometheus::IntGaugeVec = prometheus::register_int_gauge_vec!(
+        "tikv_engine_wal_recycled_files",
+        "Number of engine WAL files kept around for recycling.",
+        &["db"]
+    )
+    .unwrap();
+}
+
 pub struct EngineMetricsManager<R: RaftEngine> {
     engines: Engines<RocksEngine, R>,
+    // `host:port` of a Prometheus pushgateway. Pushing is opt-in for
+    // short-lived clusters that exit before a scrape; normal deployments
+    // keep the pull model.
+    push_gateway: Option<String>,
+    job: String,
     last_reset: Instant,
 }
 
 impl<R: RaftEngine> EngineMetricsManager<R> {
-    pub fn new(engines: Engines<RocksEngine, R>) -> Self {
+    pub fn new(
+        engines: Engines<RocksEngine, R>,
+        push_gateway: Option<String>,
+        job: String,
+    ) -> Self {
         EngineMetricsManager {
             engines,
+            push_gateway,
+            job,
             last_reset: Instant::now(),
         }
     }
@@ -1430,6 +1641,19 @@ impl<R: RaftEngine> EngineMetricsManager<R> {
     pub fn flush(&mut self, now: Instant) {
         self.engines.kv.flush_metrics("kv");
         self.engines.raft.flush_metrics("raft");
+        if let Ok(stats) = self.engines.raft.wal_stats() {
+            ENGINE_WAL_SIZE_GAUGE
+                .with_label_values(&["raft"])
+                .set(stats.total_size as i64);
+            ENGINE_WAL_RECYCLED_FILES_GAUGE
+                .with_label_values(&["raft"])
+                .set(stats.recycled_file_count as i64);
+        }
+        if let Some(addr) = self.push_gateway.as_ref() {
+            if let Err(e) = push_metrics(addr, &self.job) {
+                warn!("failed to push metrics to pushgateway"; "addr" => addr, "err" => ?e);
+            }
+        }
         if now.saturating_duration_since(self.last_reset) >= DEFAULT_ENGINE_METRICS_RESET_INTERVAL {
             self.engines.kv.reset_statistics();
             self.engines.raft.reset_statistics();
@@ -1438,11 +1662,56 @@ impl<R: RaftEngine> EngineMetricsManager<R> {
     }
 }
 
+/// POSTs all registered metrics in text format to the pushgateway at `addr`
+/// (`host:port`). A plain blocking HTTP/1.1 request is enough for the
+/// pushgateway protocol and keeps the seldom used path dependency-free.
+fn push_metrics(addr: &str, job: &str) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind, Read, Write};
+
+    let body = tikv_util::metrics::dump();
+    let mut stream = std::net::TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(2)))?;
+    write!(
+        stream,
+        "POST /metrics/job/{} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        job,
+        addr,
+        body.len(),
+        body
+    )?;
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp)?;
+    let status_line = resp.lines().next().unwrap_or("");
+    if status_line.starts_with("HTTP/1.1 2") || status_line.starts_with("HTTP/1.0 2") {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::Other,
+            format!("pushgateway responded: {}", status_line),
+        ))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref ENGINE_WRITE_STALL_COUNTER: prometheus::IntCounterVec =
+        prometheus::register_int_counter_vec!(
+            "tikv_server_engine_write_stall_conditions_total",
+            "Number of detected RocksDB write stall conditions per engine and cf.",
+            &["db", "cf"]
+        )
+        .unwrap();
+}
+
 pub struct EnginesResourceInfo {
     kv_engine: RocksEngine,
     raft_engine: Option<RocksEngine>,
+    titan_enabled: bool,
+    budget_curve: IOBudgetCurve,
+    budget_floor: f32,
     latest_normalized_pending_bytes: AtomicU32,
     normalized_pending_bytes_collector: MovingAvgU32,
+    write_stall_detected: AtomicBool,
 }
 
 impl EnginesResourceInfo {
@@ -1451,40 +1720,98 @@ impl EnginesResourceInfo {
     pub fn new(
         kv_engine: RocksEngine,
         raft_engine: Option<RocksEngine>,
+        titan_enabled: bool,
+        budget_curve: IOBudgetCurve,
+        budget_floor: f32,
         max_samples_to_preserve: usize,
     ) -> Self {
         EnginesResourceInfo {
             kv_engine,
             raft_engine,
+            titan_enabled,
+            budget_curve,
+            budget_floor,
             latest_normalized_pending_bytes: AtomicU32::new(0),
             normalized_pending_bytes_collector: MovingAvgU32::new(max_samples_to_preserve),
+            write_stall_detected: AtomicBool::new(false),
         }
     }
 
     pub fn update(&self, _now: Instant) {
         let mut normalized_pending_bytes = 0;
-
-        fn fetch_engine_cf(engine: &RocksEngine, cf: &str, normalized_pending_bytes: &mut u32) {
+        let mut stalled = false;
+
+        fn fetch_engine_cf(
+            engine: &RocksEngine,
+            db: &str,
+            cf: &str,
+            titan_enabled: bool,
+            normalized_pending_bytes: &mut u32,
+            stalled: &mut bool,
+        ) {
             if let Ok(cf_opts) = engine.get_options_cf(cf) {
-                if let Ok(Some(b)) = engine.get_cf_compaction_pending_bytes(cf) {
-                    if cf_opts.get_soft_pending_compaction_bytes_limit() > 0 {
+                let soft_limit = cf_opts.get_soft_pending_compaction_bytes_limit();
+                let mut pending_bytes = 0;
+                if let Ok(Some(mut b)) = engine.get_cf_compaction_pending_bytes(cf) {
+                    // Titan GCs blob files outside the LSM tree, so its backlog
+                    // is not covered by pending compaction bytes. Fold it in to
+                    // avoid underestimating write pressure on Titan deployments.
+                    if titan_enabled {
+                        if let Ok(Some(blob_b)) = engine.get_cf_blob_gc_pending_bytes(cf) {
+                            b += blob_b;
+                        }
+                    }
+                    pending_bytes = b;
+                    if soft_limit > 0 {
                         *normalized_pending_bytes = std::cmp::max(
                             *normalized_pending_bytes,
-                            (b * EnginesResourceInfo::SCALE_FACTOR
-                                / cf_opts.get_soft_pending_compaction_bytes_limit())
-                                as u32,
+                            (b * EnginesResourceInfo::SCALE_FACTOR / soft_limit) as u32,
                         );
                     }
                 }
+                // Check the same conditions RocksDB evaluates before delaying
+                // writes: too many level-0 files, too many immutable memtables,
+                // or pending compaction bytes over the soft limit.
+                let mut stall = soft_limit > 0 && pending_bytes >= soft_limit;
+                let l0_trigger = cf_opts.get_level_zero_slowdown_writes_trigger();
+                if l0_trigger > 0 {
+                    if let Ok(Some(n)) = engine.get_cf_num_files_at_level(cf, 0) {
+                        stall |= n >= l0_trigger as u64;
+                    }
+                }
+                if let Ok(Some(n)) = engine.get_cf_num_immutable_mem_table(cf) {
+                    // Writes stall once all write buffers are unflushed, i.e.
+                    // only the active memtable is left mutable.
+                    stall |= n + 1 >= cf_opts.get_max_write_buffer_number() as u64;
+                }
+                if stall {
+                    ENGINE_WRITE_STALL_COUNTER.with_label_values(&[db, cf]).inc();
+                    *stalled = true;
+                }
             }
         }
 
         if let Some(raft_engine) = &self.raft_engine {
-            fetch_engine_cf(raft_engine, CF_DEFAULT, &mut normalized_pending_bytes);
+            fetch_engine_cf(
+                raft_engine,
+                "raft",
+                CF_DEFAULT,
+                false,
+                &mut normalized_pending_bytes,
+                &mut stalled,
+            );
         }
         for cf in &[CF_DEFAULT, CF_WRITE, CF_LOCK] {
-            fetch_engine_cf(&self.kv_engine, cf, &mut normalized_pending_bytes);
+            fetch_engine_cf(
+                &self.kv_engine,
+                "kv",
+                cf,
+                self.titan_enabled,
+                &mut normalized_pending_bytes,
+                &mut stalled,
+            );
         }
+        self.write_stall_detected.store(stalled, Ordering::Relaxed);
         let (_, avg) = self
             .normalized_pending_bytes_collector
             .add(normalized_pending_bytes);
@@ -1497,18 +1824,242 @@ impl EnginesResourceInfo {
 
 impl IOBudgetAdjustor for EnginesResourceInfo {
     fn adjust(&self, total_budgets: usize) -> usize {
-        let score = self.latest_normalized_pending_bytes.load(Ordering::Relaxed) as f32
-            / Self::SCALE_FACTOR as f32;
-        // Two reasons for adding `sqrt` on top:
+        // A stall means the engine is already refusing writes, so don't wait
+        // for pending bytes to build up: report max pressure right away.
+        let score = if self.write_stall_detected.load(Ordering::Relaxed) {
+            1.0
+        } else {
+            self.latest_normalized_pending_bytes.load(Ordering::Relaxed) as f32
+                / Self::SCALE_FACTOR as f32
+        };
+        // Two reasons why `sqrt` is the default curve:
         // 1) In theory the convergence point is independent of the value of pending
         //    bytes (as long as backlog generating rate equals consuming rate, which is
         //    determined by compaction budgets), a convex helps reach that point while
         //    maintaining low level of pending bytes.
         // 2) Variance of compaction pending bytes grows with its magnitude, a filter
         //    with decreasing derivative can help balance such trend.
-        let score = score.sqrt();
-        // The target global write flow slides between Bandwidth / 2 and Bandwidth.
-        let score = 0.5 + score / 2.0;
+        // The target global write flow slides between Bandwidth * floor and
+        // Bandwidth, so compaction keeps its guaranteed share even under
+        // sustained write pressure.
+        let score = self.budget_curve.transform_with_floor(score, self.budget_floor);
         (total_budgets as f32 * score) as usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_rocks::raw::{ColumnFamilyOptions, DBOptions, TitanDBOptions};
+    use engine_rocks::raw_util::{new_engine_opt, CFOptions};
+    use engine_traits::{MiscExt, SyncMutable};
+    use tempfile::Builder;
+
+    #[test]
+    fn test_disk_space_exhausted() {
+        // No WAL dirs keeps the old behavior.
+        assert!(!disk_space_exhausted(100, &[], 10));
+        assert!(disk_space_exhausted(10, &[], 10));
+
+        // A WAL dir sharing the data mount doesn't change the result.
+        assert!(!disk_space_exhausted(100, &[100], 10));
+        assert!(disk_space_exhausted(10, &[10], 10));
+
+        // A separate full WAL mount trips the check on its own.
+        assert!(disk_space_exhausted(100, &[0], 10));
+        assert!(disk_space_exhausted(100, &[200, 5], 10));
+        assert!(!disk_space_exhausted(100, &[200, 50], 10));
+    }
+
+    #[test]
+    fn test_inodes_exhausted() {
+        // Zero threshold disables the check no matter how low inodes go.
+        assert!(!inodes_exhausted(&[0], 0));
+        assert!(!inodes_exhausted(&[], 0));
+
+        // Low inodes trip the check even with ample bytes elsewhere.
+        assert!(inodes_exhausted(&[5], 10));
+        assert!(inodes_exhausted(&[100, 5], 10));
+        assert!(!inodes_exhausted(&[100, 50], 10));
+        // No reporting mounts means no verdict.
+        assert!(!inodes_exhausted(&[], 10));
+    }
+
+    #[test]
+    fn test_push_metrics() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut req = Vec::new();
+            let mut buf = [0; 4096];
+            // Read headers and then the advertised body length.
+            let body_len = loop {
+                let n = stream.read(&mut buf).unwrap();
+                req.extend_from_slice(&buf[..n]);
+                let req = String::from_utf8_lossy(&req);
+                if let Some(pos) = req.find("\r\n\r\n") {
+                    let len = req
+                        .lines()
+                        .find_map(|l| l.strip_prefix("Content-Length: "))
+                        .unwrap()
+                        .parse::<usize>()
+                        .unwrap();
+                    break pos + 4 + len;
+                }
+            };
+            while req.len() < body_len {
+                let n = stream.read(&mut buf).unwrap();
+                req.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&req).into_owned()
+        });
+
+        push_metrics(&addr, "test_job").unwrap();
+        let req = handle.join().unwrap();
+        assert!(req.starts_with("POST /metrics/job/test_job HTTP/1.1\r\n"));
+
+        // A non-2xx status is surfaced as an error.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let _ = stream.read(&mut buf).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 500 Internal Server Error\r\n\r\n")
+                .unwrap();
+        });
+        push_metrics(&addr, "test_job").unwrap_err();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_engines_resource_info_with_titan() {
+        let path = Builder::new()
+            .prefix("test_engines_resource_info_with_titan")
+            .tempdir()
+            .unwrap();
+        let mut db_opts = DBOptions::new();
+        let mut titan_opts = TitanDBOptions::new();
+        titan_opts.set_min_blob_size(0);
+        db_opts.set_titandb_options(&titan_opts);
+        let cfs_opts = ALL_CFS
+            .iter()
+            .map(|cf| {
+                let mut cf_opts = ColumnFamilyOptions::new();
+                cf_opts.set_titandb_options(&titan_opts);
+                CFOptions::new(cf, cf_opts)
+            })
+            .collect();
+        let db = new_engine_opt(path.path().to_str().unwrap(), db_opts, cfs_opts).unwrap();
+        let kv_engine = RocksEngine::from_db(Arc::new(db));
+        for i in 0..10u8 {
+            kv_engine.put(&[i], &[i]).unwrap();
+        }
+        kv_engine.flush(true).unwrap();
+
+        // Titan stats must be readable when Titan is enabled.
+        for cf in ALL_CFS {
+            assert!(
+                kv_engine
+                    .get_cf_blob_gc_pending_bytes(cf)
+                    .unwrap()
+                    .is_some()
+            );
+        }
+
+        let engines_info =
+            EnginesResourceInfo::new(
+                kv_engine,
+                None,
+                true, /*titan_enabled*/
+                IOBudgetCurve::Sqrt,
+                0.5,
+                10,
+            );
+        engines_info.update(Instant::now());
+        // With no GC backlog the blob pending bytes must not inflate the score.
+        assert_eq!(
+            engines_info
+                .latest_normalized_pending_bytes
+                .load(Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[test]
+    fn test_engines_resource_info_write_stall() {
+        let path = Builder::new()
+            .prefix("test_engines_resource_info_write_stall")
+            .tempdir()
+            .unwrap();
+        let cfs_opts = ALL_CFS
+            .iter()
+            .map(|cf| {
+                let mut cf_opts = ColumnFamilyOptions::new();
+                // Two level-0 files are enough to hit the slowdown trigger.
+                // Disable auto compactions so the files stay in level 0.
+                cf_opts.set_level_zero_slowdown_writes_trigger(2);
+                cf_opts.set_disable_auto_compactions(true);
+                CFOptions::new(cf, cf_opts)
+            })
+            .collect();
+        let db = new_engine_opt(path.path().to_str().unwrap(), DBOptions::new(), cfs_opts).unwrap();
+        let kv_engine = RocksEngine::from_db(Arc::new(db));
+
+        let engines_info =
+            EnginesResourceInfo::new(kv_engine.clone(), None, false, IOBudgetCurve::Sqrt, 0.5, 10);
+        engines_info.update(Instant::now());
+        assert!(!engines_info.write_stall_detected.load(Ordering::Relaxed));
+        // Without pressure only half of the budgets go to low-priority IO.
+        assert_eq!(engines_info.adjust(1000), 500);
+
+        let stall_counter = ENGINE_WRITE_STALL_COUNTER.with_label_values(&["kv", CF_DEFAULT]);
+        let before = stall_counter.get();
+        for i in 0..2u8 {
+            kv_engine.put(&[i], &[i]).unwrap();
+            kv_engine.flush(true).unwrap();
+        }
+        engines_info.update(Instant::now());
+        // The stall condition must be counted and drive the adjustor to max
+        // pressure immediately, without any pending compaction bytes.
+        assert!(engines_info.write_stall_detected.load(Ordering::Relaxed));
+        assert!(stall_counter.get() > before);
+        assert_eq!(engines_info.adjust(1000), 1000);
+    }
+
+    #[test]
+    fn test_check_clock_drift() {
+        struct SkewedPdClient(u64);
+        impl PdClient for SkewedPdClient {
+            fn get_tso(&self) -> pd_client::PdFuture<txn_types::TimeStamp> {
+                let ts = txn_types::TimeStamp::compose(self.0, 0);
+                Box::pin(futures::future::ok(ts))
+            }
+        }
+
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        // An aligned clock is within any sane threshold.
+        assert!(check_clock_drift(
+            &SkewedPdClient(now_ms),
+            Duration::from_secs(10),
+            false
+        ));
+        // A PD clock one minute ahead must trip the warning.
+        assert!(!check_clock_drift(
+            &SkewedPdClient(now_ms + 60_000),
+            Duration::from_millis(500),
+            false
+        ));
+    }
+}
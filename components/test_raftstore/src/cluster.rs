@@ -2,6 +2,7 @@
 
 use std::collections::hash_map::Entry;
 use std::error::Error as StdError;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex, RwLock};
 use std::time::Duration;
 use std::{result, thread};
@@ -12,9 +13,10 @@ use kvproto::metapb::{self, PeerRole, RegionEpoch, StoreLabel};
 use kvproto::pdpb;
 use kvproto::raft_cmdpb::*;
 use kvproto::raft_serverpb::{
-    self, RaftApplyState, RaftLocalState, RaftMessage, RaftTruncatedState, RegionLocalState,
+    self, PeerState, RaftApplyState, RaftLocalState, RaftMessage, RaftTruncatedState,
+    RegionLocalState,
 };
-use raft::eraftpb::ConfChangeType;
+use raft::eraftpb::{ConfChangeType, MessageType};
 use tempfile::TempDir;
 
 use collections::{HashMap, HashSet};
@@ -23,20 +25,23 @@ use engine_rocks::raw::DB;
 use engine_rocks::{Compat, RocksEngine, RocksSnapshot};
 use engine_traits::{
     CompactExt, Engines, Iterable, MiscExt, Mutable, Peekable, WriteBatch, WriteBatchExt,
-    CF_DEFAULT, CF_RAFT,
+    CF_DEFAULT, CF_RAFT, CF_WRITE, DATA_CFS,
 };
 use file_system::IORateLimiter;
 use pd_client::PdClient;
+use raftstore::coprocessor::{BoxAdminObserver, BoxQueryObserver, CoprocessorHost};
 use raftstore::store::fsm::store::{StoreMeta, PENDING_MSG_CAP};
 use raftstore::store::fsm::{create_raft_batch_system, RaftBatchSystem, RaftRouter};
 use raftstore::store::transport::CasualRouter;
 use raftstore::store::*;
 use raftstore::{Error, Result};
 use tikv::config::TiKvConfig;
+use tikv::server::gc_worker::{sync_gc, GcTask};
 use tikv::server::Result as ServerResult;
+use tikv_util::worker::FutureScheduler;
 use tikv_util::thread_group::GroupProperties;
 use tikv_util::time::Instant;
-use tikv_util::HandyRwLock;
+use tikv_util::{escape, HandyRwLock};
 
 use super::*;
 use tikv_util::time::ThreadReadId;
@@ -78,6 +83,24 @@ pub trait Simulator {
     fn clear_send_filters(&mut self, node_id: u64);
     fn add_recv_filter(&mut self, node_id: u64, filter: Box<dyn Filter>);
     fn clear_recv_filters(&mut self, node_id: u64);
+    /// Registers a hook that will be invoked on the node's `CoprocessorHost`
+    /// when the node starts. Must be called before `run_node`.
+    fn add_coprocessor_hook(
+        &mut self,
+        node_id: u64,
+        hook: Box<dyn Fn(&mut CoprocessorHost<RocksEngine>)>,
+    );
+    /// Returns the scheduler of the node's GC worker if the simulator runs
+    /// one. Only server clusters spawn GC workers.
+    fn get_gc_scheduler(&self, _node_id: u64) -> Option<FutureScheduler<GcTask<RocksEngine>>> {
+        None
+    }
+    /// Returns the largest GC safe point the node's GC worker has completed
+    /// a task for, if the simulator runs one. Only server clusters spawn GC
+    /// workers.
+    fn get_gc_safe_point(&self, _node_id: u64) -> Option<u64> {
+        None
+    }
 
     fn call_command(&self, request: RaftCmdRequest, timeout: Duration) -> Result<RaftCmdResponse> {
         let node_id = request.get_header().get_peer().get_store_id();
@@ -126,6 +149,14 @@ pub trait Simulator {
     }
 }
 
+/// Per-operation latency distribution collected by `Cluster::bench_writes`.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyStats {
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
 pub struct Cluster<T: Simulator> {
     pub cfg: TiKvConfig,
     leaders: HashMap<u64, metapb::Peer>,
@@ -140,6 +171,12 @@ pub struct Cluster<T: Simulator> {
     key_managers_map: HashMap<u64, Option<Arc<DataKeyManager>>>,
     pub labels: HashMap<u64, HashMap<String, String>>,
     group_props: HashMap<u64, GroupProperties>,
+    // The clock offset currently injected per node, see `set_clock_offset`.
+    clock_offsets: HashMap<u64, Duration>,
+    // Stores killed by `kill_store_permanently`, never auto-restarted.
+    dead_stores: HashSet<u64>,
+    // The time source consulted by polling helpers, see `set_clock`.
+    clock: Arc<dyn TestClock>,
 
     pub sim: Arc<RwLock<T>>,
     pub pd_client: Arc<TestPdClient>,
@@ -167,6 +204,9 @@ impl<T: Simulator> Cluster<T> {
             key_managers_map: HashMap::default(),
             labels: HashMap::default(),
             group_props: HashMap::default(),
+            clock_offsets: HashMap::default(),
+            dead_stores: HashSet::default(),
+            clock: Arc::new(RealClock::default()),
             sim,
             pd_client,
         }
@@ -225,6 +265,10 @@ impl<T: Simulator> Cluster<T> {
         // Try recover from last shutdown.
         let node_ids: Vec<u64> = self.engines.iter().map(|(&id, _)| id).collect();
         for node_id in node_ids {
+            // Permanently killed stores must stay dead.
+            if self.dead_stores.contains(&node_id) {
+                continue;
+            }
             self.run_node(node_id)?;
         }
 
@@ -272,6 +316,127 @@ impl<T: Simulator> Cluster<T> {
         }
     }
 
+    /// Advances the GC safe point to `safe_point` and physically reclaims
+    /// stale MVCC versions in the range of the given region.
+    ///
+    /// GC bypasses Raft since v5.0, so every store hosting the region is
+    /// GCed separately, followed by a manual compaction of the range so
+    /// that deletion markers written by GC are really dropped from SST
+    /// files. Requires a simulator that runs GC workers, e.g. server
+    /// clusters.
+    pub fn must_gc_and_compact(&mut self, region_id: u64, safe_point: u64) {
+        self.pd_client.set_gc_safe_point(safe_point);
+        let region = block_on(self.pd_client.get_region_by_id(region_id))
+            .unwrap()
+            .unwrap_or_else(|| panic!("region {} doesn't exist", region_id));
+        let start = keys::data_key(region.get_start_key());
+        let end = keys::data_end_key(region.get_end_key());
+        for peer in region.get_peers() {
+            let store_id = peer.get_store_id();
+            let sched = self
+                .sim
+                .rl()
+                .get_gc_scheduler(store_id)
+                .expect("simulator doesn't run a GC worker");
+            sync_gc(
+                &sched,
+                region_id,
+                region.get_start_key().to_vec(),
+                region.get_end_key().to_vec(),
+                safe_point.into(),
+            )
+            .unwrap();
+            let engine = &self.engines[&store_id].kv;
+            for cf in &[CF_WRITE, CF_DEFAULT] {
+                engine
+                    .compact_range(cf, Some(&start), Some(&end), false, 1)
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Returns the GC safe point currently applied on the store, i.e. the
+    /// largest safe point its GC worker has completed a task for, or 0 if
+    /// no safe point has been applied yet. Requires a simulator that runs
+    /// GC workers, e.g. server clusters.
+    pub fn store_gc_safe_point(&self, node_id: u64) -> u64 {
+        self.sim
+            .rl()
+            .get_gc_safe_point(node_id)
+            .expect("simulator doesn't run a GC worker")
+    }
+
+    /// Waits until the GC safe point applied on the store reaches at least
+    /// `ts`, so tests can assert on reclaimed data without guessing whether
+    /// the safe point has propagated to the node.
+    pub fn wait_gc_safe_point_gte(&self, node_id: u64, ts: u64, timeout: Duration) {
+        let timer = Instant::now();
+        loop {
+            let current = self.store_gc_safe_point(node_id);
+            if current >= ts {
+                return;
+            }
+            if timer.saturating_elapsed() >= timeout {
+                panic!(
+                    "gc safe point on store {} is {}, expect >= {} after {:?}",
+                    node_id, current, ts, timeout
+                );
+            }
+            sleep_ms(20);
+        }
+    }
+
+    /// Computes a checksum of the region's user data on the given store, so
+    /// tests can compare replicas with each other or with a baseline.
+    ///
+    /// The algorithm mirrors the raftstore consistency check: a CRC32
+    /// (crc32fast, IEEE) digest updated with every key and value in the
+    /// region's data range, scanning the `DATA_CFS` in sorted name order.
+    /// Unlike the consistency check it leaves out the region state, which
+    /// legitimately differs across replicas.
+    pub fn region_data_checksum(&self, region_id: u64, store_id: u64) -> u64 {
+        let region = block_on(self.pd_client.get_region_by_id(region_id))
+            .unwrap()
+            .unwrap_or_else(|| panic!("region {} doesn't exist", region_id));
+        let start_key = keys::enc_start_key(&region);
+        let end_key = keys::enc_end_key(&region);
+        let mut digest = crc32fast::Hasher::new();
+        let mut cfs = DATA_CFS.to_vec();
+        cfs.sort_unstable();
+        for cf in cfs {
+            self.engines[&store_id]
+                .kv
+                .scan_cf(cf, &start_key, &end_key, false, |k, v| {
+                    digest.update(k);
+                    digest.update(v);
+                    Ok(true)
+                })
+                .unwrap();
+        }
+        u64::from(digest.finalize())
+    }
+
+    /// Counts the physical versions of `key` that remain in the write CF of
+    /// the given store, including versions a read would skip. Useful to
+    /// assert that GC reclaimed old versions.
+    pub fn count_write_versions(&self, store_id: u64, key: &[u8]) -> usize {
+        let prefix = keys::data_key(txn_types::Key::from_raw(key).as_encoded());
+        let mut versions = 0;
+        self.engines[&store_id]
+            .kv
+            .scan_cf(CF_WRITE, &prefix, keys::DATA_MAX_KEY, false, |k, _| {
+                // The memcomparable encoding is prefix free between different
+                // raw keys, so a prefix match means a version of `key`.
+                if !k.starts_with(&prefix) {
+                    return Ok(false);
+                }
+                versions += 1;
+                Ok(true)
+            })
+            .unwrap();
+        versions
+    }
+
     // Bootstrap the store with fixed ID (like 1, 2, .. 5) and
     // initialize first region in all stores, then start the cluster.
     pub fn run(&mut self) {
@@ -294,14 +459,26 @@ impl<T: Simulator> Cluster<T> {
     }
 
     pub fn run_node(&mut self, node_id: u64) -> ServerResult<()> {
+        self.run_node_with(node_id, |_| {})
+    }
+
+    /// Like `run_node`, but applies `f` to the node's cloned config before
+    /// starting it, so a single node can run with settings different from
+    /// the rest of the cluster, e.g. another raft tick interval.
+    pub fn run_node_with<F: FnOnce(&mut TiKvConfig)>(
+        &mut self,
+        node_id: u64,
+        f: F,
+    ) -> ServerResult<()> {
         debug!("starting node {}", node_id);
         let engines = self.engines[&node_id].clone();
         let key_mgr = self.key_managers_map[&node_id].clone();
-        let (router, system) = create_raft_batch_system(&self.cfg.raft_store);
         let mut cfg = self.cfg.clone();
         if let Some(labels) = self.labels.get(&node_id) {
             cfg.server.labels = labels.to_owned();
         }
+        f(&mut cfg);
+        let (router, system) = create_raft_batch_system(&cfg.raft_store);
         let store_meta = match self.store_metas.entry(node_id) {
             Entry::Occupied(o) => {
                 let mut meta = o.get().lock().unwrap();
@@ -324,6 +501,26 @@ impl<T: Simulator> Cluster<T> {
         Ok(())
     }
 
+    /// Installs an observer on the given node that counts how many times each
+    /// coprocessor hook fires and returns a handle to read the counters.
+    ///
+    /// Must be called before the node is started, so the observer can be
+    /// registered when the node's coprocessor host is created.
+    pub fn install_counting_observer(&mut self, node_id: u64) -> Arc<ObserverCounts> {
+        let counts = Arc::new(ObserverCounts::default());
+        let ob = CountingObserver::new(counts.clone());
+        self.sim.wl().add_coprocessor_hook(
+            node_id,
+            Box::new(move |host| {
+                host.registry
+                    .register_admin_observer(100, BoxAdminObserver::new(ob.clone()));
+                host.registry
+                    .register_query_observer(100, BoxQueryObserver::new(ob.clone()));
+            }),
+        );
+        counts
+    }
+
     pub fn stop_node(&mut self, node_id: u64) {
         debug!("stopping node {}", node_id);
         self.group_props[&node_id].mark_shutdown();
@@ -335,6 +532,78 @@ impl<T: Simulator> Cluster<T> {
         debug!("node {} stopped", node_id);
     }
 
+    /// Sets `failpoint` to `action`, stops the node so the configured fault
+    /// fires on its shutdown path, then clears the failpoint and restarts the
+    /// node. Combined with `must_get` this standardizes crash-consistency
+    /// tests: committed keys must survive the restart, uncommitted ones must
+    /// not show up.
+    ///
+    /// The failpoint is cleared even if stopping the node panics, so one
+    /// failed case doesn't poison the following ones.
+    pub fn restart_node_with_failpoint(&mut self, node_id: u64, failpoint: &str, action: &str) {
+        struct ClearFailpoint<'a>(&'a str);
+        impl Drop for ClearFailpoint<'_> {
+            fn drop(&mut self) {
+                fail::remove(self.0);
+            }
+        }
+
+        fail::cfg(failpoint, action).unwrap();
+        {
+            let _guard = ClearFailpoint(failpoint);
+            self.stop_node(node_id);
+        }
+        self.run_node(node_id).unwrap();
+    }
+
+    /// Stops the node like `stop_node`, but treats the failure as permanent:
+    /// the store is re-registered in PD as tombstone so scheduling sees it as
+    /// dead, and `start` won't bring it back. Use `replace_dead_store` to add
+    /// a fresh store in its place for replica-recovery tests.
+    pub fn kill_store_permanently(&mut self, node_id: u64) {
+        let mut store = self
+            .pd_client
+            .get_store(node_id)
+            .unwrap_or_else(|_| panic!("store {} not registered in pd", node_id));
+        self.stop_node(node_id);
+        // `stop_node` removed the store from PD entirely; put it back as
+        // tombstone so the permanent failure is observable.
+        store.set_state(metapb::StoreState::Tombstone);
+        self.pd_client.put_store(store).unwrap();
+        self.dead_stores.insert(node_id);
+    }
+
+    /// Brings up a brand-new store with a freshly allocated id, typically to
+    /// replace one killed with `kill_store_permanently`. Returns the new
+    /// store id; moving replicas onto it is left to the caller.
+    pub fn replace_dead_store(&mut self) -> ServerResult<u64> {
+        let (router, system) = create_raft_batch_system(&self.cfg.raft_store);
+        self.create_engine(Some(router.clone()));
+
+        let engines = self.dbs.last().unwrap().clone();
+        let key_mgr = self.key_managers.last().unwrap().clone();
+        let store_meta = Arc::new(Mutex::new(StoreMeta::new(PENDING_MSG_CAP)));
+
+        let props = GroupProperties::default();
+        tikv_util::thread_group::set_properties(Some(props.clone()));
+
+        let node_id = self.sim.wl().run_node(
+            0,
+            self.cfg.clone(),
+            engines.clone(),
+            store_meta.clone(),
+            key_mgr.clone(),
+            router,
+            system,
+        )?;
+        self.group_props.insert(node_id, props);
+        self.engines.insert(node_id, engines);
+        self.store_metas.insert(node_id, store_meta);
+        self.key_managers_map.insert(node_id, key_mgr);
+        self.count += 1;
+        Ok(node_id)
+    }
+
     pub fn get_engine(&self, node_id: u64) -> Arc<DB> {
         Arc::clone(&self.engines[&node_id].kv.as_inner())
     }
@@ -414,12 +683,31 @@ impl<T: Simulator> Cluster<T> {
     }
 
     pub fn call_command_on_leader(
+        &mut self,
+        request: RaftCmdRequest,
+        timeout: Duration,
+    ) -> Result<RaftCmdResponse> {
+        self.call_command_on_leader_with_opts(request, timeout, None)
+    }
+
+    /// Like `call_command_on_leader`, but gives up after `max_attempts`
+    /// calls if given, and sleeps with capped exponential backoff between
+    /// retries so a long election doesn't spin hot and flood the log with
+    /// not-leader warnings. When attempts run out the last response is
+    /// returned as is, with the not-leader error still in its header.
+    pub fn call_command_on_leader_with_opts(
         &mut self,
         mut request: RaftCmdRequest,
         timeout: Duration,
+        max_attempts: Option<usize>,
     ) -> Result<RaftCmdResponse> {
-        let timer = Instant::now();
+        const BACKOFF_INIT: Duration = Duration::from_millis(10);
+        const BACKOFF_MAX: Duration = Duration::from_millis(200);
+        let clock = self.clock.clone();
+        let start = clock.now();
         let region_id = request.get_header().get_region_id();
+        let mut backoff = BACKOFF_INIT;
+        let mut attempts = 0;
         loop {
             let leader = match self.leader_of_region(region_id) {
                 None => return Err(Error::NotLeader(region_id, None)),
@@ -430,13 +718,17 @@ impl<T: Simulator> Cluster<T> {
                 e @ Err(_) => return e,
                 Ok(resp) => resp,
             };
+            attempts += 1;
             if self.refresh_leader_if_needed(&resp, region_id)
-                && timer.saturating_elapsed() < timeout
+                && max_attempts.map_or(true, |max| attempts < max)
+                && clock.now().saturating_sub(start) < timeout
             {
                 warn!(
                     "{:?} is no longer leader, let's retry",
                     request.get_header().get_peer()
                 );
+                clock.sleep(backoff);
+                backoff = std::cmp::min(backoff * 2, BACKOFF_MAX);
                 continue;
             }
             return Ok(resp);
@@ -667,6 +959,50 @@ impl<T: Simulator> Cluster<T> {
             .insert(key.to_owned(), value.to_owned());
     }
 
+    /// Replaces the labels of the node at runtime and pushes them to PD
+    /// right away, so placement tests can exercise dynamic label changes
+    /// without restarting the node.
+    pub fn update_store_labels(&mut self, node_id: u64, labels: &[(String, String)]) {
+        let entry = self.labels.entry(node_id).or_default();
+        entry.clear();
+        for (key, value) in labels {
+            entry.insert(key.clone(), value.clone());
+        }
+        let mut store = self.pd_client.get_store(node_id).unwrap();
+        store.labels.clear();
+        for (key, value) in labels {
+            store.labels.push(StoreLabel {
+                key: key.clone(),
+                value: value.clone(),
+                ..Default::default()
+            });
+        }
+        self.pd_client.put_store(store).unwrap();
+    }
+
+    /// Polls PD until the store's labels contain every expected pair,
+    /// panicking on timeout. Labels not listed in `expected` are ignored.
+    pub fn must_store_has_labels(&mut self, store_id: u64, expected: &[(String, String)]) {
+        for _ in 0..250 {
+            let store = self.pd_client.get_store(store_id).unwrap();
+            let labels: Vec<_> = store
+                .get_labels()
+                .iter()
+                .map(|l| (l.get_key().to_owned(), l.get_value().to_owned()))
+                .collect();
+            if expected.iter().all(|pair| labels.contains(pair)) {
+                return;
+            }
+            sleep_ms(20);
+        }
+        panic!(
+            "[store {}] labels {:?} don't contain {:?}",
+            store_id,
+            self.pd_client.get_store(store_id).unwrap().get_labels(),
+            expected
+        );
+    }
+
     pub fn add_new_engine(&mut self) -> u64 {
         self.create_engine(None);
         self.count += 1;
@@ -683,6 +1019,13 @@ impl<T: Simulator> Cluster<T> {
         node_id
     }
 
+    /// Clears every cached leader, so subsequent `leader_of_region` calls
+    /// re-discover leaders from scratch. Handy after a batch of transfers
+    /// or restarts that invalidates most of the cache at once.
+    pub fn reset_all_leaders(&mut self) {
+        self.leaders.clear();
+    }
+
     pub fn reset_leader_of_region(&mut self, region_id: u64) {
         self.leaders.remove(&region_id);
     }
@@ -980,6 +1323,41 @@ impl<T: Simulator> Cluster<T> {
         }
     }
 
+    /// Issues `count` sequential puts of `value_size`-byte values to the
+    /// region owning `region_key` and returns the per-op latency
+    /// distribution, so performance regression tests share one timing loop
+    /// instead of each rolling its own. Panics if a put fails.
+    pub fn bench_writes(
+        &mut self,
+        region_key: &[u8],
+        count: usize,
+        value_size: usize,
+    ) -> LatencyStats {
+        assert!(count > 0);
+        let value = vec![b'x'; value_size];
+        let mut latencies = Vec::with_capacity(count);
+        for _ in 0..count {
+            let timer = Instant::now();
+            let resp = self.request(
+                region_key,
+                vec![new_put_cf_cmd(CF_DEFAULT, region_key, &value)],
+                false,
+                Duration::from_secs(5),
+            );
+            if resp.get_header().has_error() {
+                panic!("bench write response {:?} has error", resp);
+            }
+            latencies.push(timer.saturating_elapsed());
+        }
+        latencies.sort_unstable();
+        let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+        LatencyStats {
+            p50: percentile(50),
+            p99: percentile(99),
+            max: *latencies.last().unwrap(),
+        }
+    }
+
     pub fn must_delete(&mut self, key: &[u8]) {
         self.must_delete_cf(CF_DEFAULT, key)
     }
@@ -1036,6 +1414,37 @@ impl<T: Simulator> Cluster<T> {
             .take_region_epoch()
     }
 
+    /// Runs `body` and asserts the region epoch advanced by exactly
+    /// `version_delta` and `conf_delta`, polling PD so region heartbeats
+    /// can catch up first. Captures the common pre/post epoch check
+    /// around splits, merges and conf changes.
+    pub fn assert_epoch_advanced<F: FnOnce(&mut Self)>(
+        &mut self,
+        region_id: u64,
+        body: F,
+        version_delta: u64,
+        conf_delta: u64,
+    ) {
+        let before = self.get_region_epoch(region_id);
+        body(self);
+        let expected_version = before.get_version() + version_delta;
+        let expected_conf_ver = before.get_conf_ver() + conf_delta;
+        let mut epoch = self.get_region_epoch(region_id);
+        for _ in 0..250 {
+            if epoch.get_version() == expected_version
+                && epoch.get_conf_ver() == expected_conf_ver
+            {
+                return;
+            }
+            sleep_ms(20);
+            epoch = self.get_region_epoch(region_id);
+        }
+        panic!(
+            "[region {}] epoch {:?} didn't advance from {:?} to version {} conf_ver {}",
+            region_id, epoch, before, expected_version, expected_conf_ver
+        );
+    }
+
     pub fn region_detail(&self, region_id: u64, store_id: u64) -> RegionDetailResponse {
         let status_cmd = new_region_detail_cmd();
         let peer = new_peer(store_id, 0);
@@ -1090,6 +1499,54 @@ impl<T: Simulator> Cluster<T> {
             .unwrap()
     }
 
+    /// Returns the current raft term of the region's peer on the store, read
+    /// from the persisted hard state. Retries briefly because the state may
+    /// not be persisted right after the peer is created.
+    pub fn raft_term(&self, region_id: u64, store_id: u64) -> u64 {
+        let key = keys::raft_state_key(region_id);
+        let timer = Instant::now();
+        loop {
+            if let Some(state) = self
+                .get_raft_engine(store_id)
+                .c()
+                .get_msg::<raft_serverpb::RaftLocalState>(&key)
+                .unwrap()
+            {
+                return state.get_hard_state().get_term();
+            }
+            if timer.saturating_elapsed() >= Duration::from_secs(5) {
+                panic!(
+                    "raft state of region {} not found on store {}",
+                    region_id, store_id
+                );
+            }
+            sleep_ms(20);
+        }
+    }
+
+    /// Records the region's term on the store, runs `body` (e.g. partition
+    /// and heal a node) and asserts the term didn't increase. This is the
+    /// pre-vote invariant: an isolated peer must not disrupt the majority
+    /// by campaigning with ever higher terms.
+    pub fn assert_term_stable_during<F: FnOnce(&mut Self)>(
+        &mut self,
+        region_id: u64,
+        store_id: u64,
+        body: F,
+    ) {
+        let before = self.raft_term(region_id, store_id);
+        body(self);
+        let after = self.raft_term(region_id, store_id);
+        assert!(
+            after <= before,
+            "term of region {} on store {} increased from {} to {}",
+            region_id,
+            store_id,
+            before,
+            after
+        );
+    }
+
     pub fn region_local_state(&self, region_id: u64, store_id: u64) -> RegionLocalState {
         self.get_engine(store_id)
             .c()
@@ -1101,6 +1558,138 @@ impl<T: Simulator> Cluster<T> {
             .unwrap()
     }
 
+    /// Enumerates ids of all regions that persisted a local state on the
+    /// store, including tombstoned ones.
+    pub fn regions_on_store(&self, store_id: u64) -> Vec<u64> {
+        let mut regions = Vec::new();
+        self.get_engine(store_id)
+            .c()
+            .scan_cf(
+                CF_RAFT,
+                keys::REGION_META_MIN_KEY,
+                keys::REGION_META_MAX_KEY,
+                false,
+                |key, _| {
+                    let (id, suffix) = keys::decode_region_meta_key(key).unwrap();
+                    if suffix == keys::REGION_STATE_SUFFIX {
+                        regions.push(id);
+                    }
+                    Ok(true)
+                },
+            )
+            .unwrap();
+        regions
+    }
+
+    /// Waits until every region on the store reaches the given `PeerState`,
+    /// panicking on timeout with the regions that lag behind. Decommission
+    /// tests use it to confirm the whole store became `Tombstone`.
+    pub fn wait_all_regions_peer_state(&self, store_id: u64, state: PeerState, timeout: Duration) {
+        let timer = Instant::now();
+        let mut laggards;
+        loop {
+            laggards = self
+                .regions_on_store(store_id)
+                .into_iter()
+                .filter(|&id| self.region_local_state(id, store_id).get_state() != state)
+                .collect::<Vec<_>>();
+            if laggards.is_empty() {
+                return;
+            }
+            if timer.saturating_elapsed() >= timeout {
+                break;
+            }
+            sleep_ms(20);
+        }
+        panic!(
+            "store {} regions {:?} fail to reach peer state {:?} after {:?}",
+            store_id, laggards, state, timeout
+        );
+    }
+
+    /// Asserts every voter of the region owning `key` stores `expected` for
+    /// it, reading each voter's kv engine directly and bypassing raft read.
+    /// Retries until `timeout` because followers may apply behind the leader.
+    pub fn must_get_equal_all_replicas(
+        &mut self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        timeout: Duration,
+    ) {
+        let timer = Instant::now();
+        let mut last_diverged;
+        loop {
+            let region = self.get_region(key);
+            let mut diverged = None;
+            for peer in region.get_peers() {
+                if peer.get_role() != PeerRole::Voter {
+                    continue;
+                }
+                let store_id = peer.get_store_id();
+                let value = self
+                    .get_engine(store_id)
+                    .c()
+                    .get_value(&keys::data_key(key))
+                    .unwrap()
+                    .map(|v| v.to_vec());
+                if value.as_deref() != expected {
+                    diverged = Some((store_id, value));
+                    break;
+                }
+            }
+            match diverged {
+                None => return,
+                Some(d) => last_diverged = d,
+            }
+            if timer.saturating_elapsed() >= timeout {
+                break;
+            }
+            sleep_ms(20);
+        }
+        let (store_id, value) = last_diverged;
+        panic!(
+            "store {} diverged for key {}: got {:?}, expect {:?}",
+            store_id,
+            log_wrappers::hex_encode_upper(key),
+            value.as_deref().map(escape),
+            expected.map(escape),
+        );
+    }
+
+    /// Returns the used size of the column family of the kv engine on the
+    /// store, summing SST, mem-table and blob file sizes.
+    pub fn engine_used_size_cf(&self, store_id: u64, cf: &str) -> u64 {
+        let engine = self.get_engine(store_id);
+        let handle = engine_rocks::util::get_cf_handle(&engine, cf).unwrap();
+        engine_rocks::util::get_engine_cf_used_size(&engine, handle)
+    }
+
+    /// Waits until the used size of the column family on the store drops to
+    /// `expected` or below, useful for compaction and tombstone reclaim
+    /// assertions. Panics on timeout.
+    pub fn wait_engine_size_cf_lte(
+        &self,
+        store_id: u64,
+        cf: &str,
+        expected: u64,
+        timeout: Duration,
+    ) {
+        let timer = Instant::now();
+        loop {
+            let size = self.engine_used_size_cf(store_id, cf);
+            if size <= expected {
+                return;
+            }
+            if timer.saturating_elapsed() >= timeout {
+                panic!(
+                    "[store {}] cf {} used size still not below {}: {}",
+                    store_id, cf, expected, size
+                );
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
     pub fn wait_last_index(
         &mut self,
         region_id: u64,
@@ -1125,6 +1714,172 @@ impl<T: Simulator> Cluster<T> {
         }
     }
 
+    /// Asserts the store's peer of the region casts no votes for `during`,
+    /// while an election is forced by isolating the current leader. Covers
+    /// pre-votes too, since a learner mistaken for a voter answers those
+    /// first. Panics when any vote message is observed.
+    ///
+    /// Note all send filters are cleared on return.
+    pub fn assert_no_votes_from(&mut self, store_id: u64, region_id: u64, during: Duration) {
+        let (filter, count) = CountingFilter::with_predicate(Arc::new(move |m: &RaftMessage| {
+            m.get_region_id() == region_id
+                && matches!(
+                    m.get_message().get_msg_type(),
+                    MessageType::MsgRequestVote
+                        | MessageType::MsgRequestVoteResponse
+                        | MessageType::MsgRequestPreVote
+                        | MessageType::MsgRequestPreVoteResponse
+                )
+        }));
+        self.sim.wl().add_send_filter(store_id, Box::new(filter));
+
+        // Cut the leader off so the rest of the region campaigns.
+        let leader = self.leader_of_region(region_id).unwrap();
+        assert_ne!(leader.get_store_id(), store_id);
+        self.add_send_filter(IsolationFilterFactory::new(leader.get_store_id()));
+
+        let timer = Instant::now();
+        while timer.saturating_elapsed() < during {
+            let votes = count.load(Ordering::SeqCst);
+            if votes > 0 {
+                panic!(
+                    "[region {}] store {} unexpectedly sent {} vote messages",
+                    region_id, store_id, votes
+                );
+            }
+            sleep_ms(20);
+        }
+        self.clear_send_filters();
+    }
+
+    /// Forces the region's leader to report a region heartbeat to PD right
+    /// away, so tests don't wait for the pd heartbeat tick. Retries briefly
+    /// when the region has no leader yet.
+    pub fn must_send_region_heartbeat(&mut self, region_id: u64) {
+        for _ in 0..300 {
+            if let Some(leader) = self.leader_of_region(region_id) {
+                let router = self.sim.rl().get_router(leader.get_store_id()).unwrap();
+                router
+                    .send(
+                        region_id,
+                        PeerMsg::CasualMessage(CasualMessage::ForceRegionHeartbeat),
+                    )
+                    .unwrap();
+                return;
+            }
+            sleep_ms(20);
+        }
+        panic!("[region {}] no leader to heartbeat pd", region_id);
+    }
+
+    /// Returns the last store heartbeat PD received from the store, or
+    /// `None` when no heartbeat has arrived yet.
+    pub fn store_stats(&self, store_id: u64) -> Option<pdpb::StoreStats> {
+        self.pd_client.get_store_stats(store_id)
+    }
+
+    /// Waits until the store reports an available size no larger than
+    /// `bytes` to PD. Panics on timeout.
+    pub fn wait_store_available_lte(&self, store_id: u64, bytes: u64, timeout: Duration) {
+        let timer = Instant::now();
+        loop {
+            let stats = self.store_stats(store_id);
+            if let Some(ref s) = stats {
+                if s.get_available() <= bytes {
+                    return;
+                }
+            }
+            if timer.saturating_elapsed() >= timeout {
+                panic!(
+                    "[store {}] available still not below {}: {:?}",
+                    store_id, bytes, stats
+                );
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    // Installs a transparent filter counting heartbeats the leader of the
+    // region sends out. Returns the counter.
+    fn count_region_heartbeats(&mut self, region_id: u64, store_id: u64) -> Arc<AtomicUsize> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_ = count.clone();
+        let filter = RegionPacketFilter::new(region_id, store_id)
+            .direction(Direction::Send)
+            .msg_type(MessageType::MsgHeartbeat)
+            // Count only, never drop.
+            .when(Arc::new(AtomicBool::new(false)))
+            .set_msg_callback(Arc::new(move |_| {
+                count_.fetch_add(1, Ordering::SeqCst);
+            }));
+        self.sim.wl().add_send_filter(store_id, Box::new(filter));
+        count
+    }
+
+    /// Waits until the region hibernates, i.e. its leader stops sending
+    /// heartbeats for a full quiet window. Panics on timeout.
+    ///
+    /// Note all send filters on the leader store are cleared on return.
+    pub fn wait_region_hibernated(&mut self, region_id: u64, timeout: Duration) {
+        let leader = self.leader_of_region(region_id).unwrap();
+        let count = self.count_region_heartbeats(region_id, leader.get_store_id());
+        // Heartbeats are sent every `raft_heartbeat_ticks` base ticks until
+        // the peer hibernates, so observing none for a bit more than two
+        // heartbeat intervals means ticking has stopped.
+        let quiet_window = self.cfg.raft_store.raft_heartbeat_interval() * 2
+            + self.cfg.raft_store.raft_base_tick_interval.0;
+        let timer = Instant::now();
+        loop {
+            let before = count.load(Ordering::SeqCst);
+            thread::sleep(quiet_window);
+            if count.load(Ordering::SeqCst) == before {
+                self.sim.wl().clear_send_filters(leader.get_store_id());
+                return;
+            }
+            if timer.saturating_elapsed() >= timeout {
+                panic!(
+                    "[region {}] leader is still sending heartbeats after {:?}",
+                    region_id, timeout
+                );
+            }
+        }
+    }
+
+    /// Wakes up a hibernated region with a quorum read and confirms its
+    /// leader starts sending heartbeats again.
+    ///
+    /// Note all send filters on the leader store are cleared on return.
+    pub fn must_wake_region(&mut self, region_id: u64) {
+        let leader = self.leader_of_region(region_id).unwrap();
+        let count = self.count_region_heartbeats(region_id, leader.get_store_id());
+        // Any proposal wakes the peer up; a quorum read has no side effect.
+        let region = block_on(self.pd_client.get_region_by_id(region_id))
+            .unwrap()
+            .unwrap();
+        let req = new_request(
+            region_id,
+            region.get_region_epoch().clone(),
+            vec![new_get_cmd(region.get_start_key())],
+            true,
+        );
+        let resp = self
+            .call_command_on_leader(req, Duration::from_secs(5))
+            .unwrap();
+        assert!(
+            !resp.get_header().has_error(),
+            "wake read failed: {:?}",
+            resp
+        );
+        let timer = Instant::now();
+        while count.load(Ordering::SeqCst) == 0 {
+            if timer.saturating_elapsed() >= Duration::from_secs(5) {
+                panic!("[region {}] leader doesn't resume ticking", region_id);
+            }
+            sleep_ms(20);
+        }
+        self.sim.wl().clear_send_filters(leader.get_store_id());
+    }
+
     pub fn restore_kv_meta(&self, region_id: u64, store_id: u64, snap: &RocksSnapshot) {
         let (meta_start, meta_end) = (
             keys::region_meta_prefix(region_id),
@@ -1193,6 +1948,133 @@ impl<T: Simulator> Cluster<T> {
         }
     }
 
+    /// Merges `source` into `target` and verifies the last write of the
+    /// source is served from the merged region, i.e. the target applied
+    /// the source's committed entries before the merge commit.
+    /// `last_source_key` must be written to the source region right before
+    /// calling this. Epoch changes and scheduling retries are handled by
+    /// the merge operator itself.
+    pub fn must_merge_and_verify_order(
+        &mut self,
+        source: u64,
+        target: u64,
+        last_source_key: &[u8],
+    ) {
+        let value = self.must_get(last_source_key).unwrap_or_else(|| {
+            panic!(
+                "key {} must be written to the source region before merging",
+                escape(last_source_key)
+            )
+        });
+        self.pd_client.must_merge(source, target);
+        // The source is gone, so its range including the freshly written
+        // key has to be covered by the target now.
+        let region = self.get_region(last_source_key);
+        assert_eq!(
+            region.get_id(),
+            target,
+            "key {} should be covered by the target region after merge",
+            escape(last_source_key)
+        );
+        let merged_value = self.must_get(last_source_key);
+        assert_eq!(
+            merged_value.as_deref(),
+            Some(value.as_slice()),
+            "the source's last write must survive the merge"
+        );
+    }
+
+    /// Returns descriptions of the operators PD has scheduled for the
+    /// region and not yet finished. See `TestPdClient::pending_operators`.
+    pub fn pending_pd_operators(&self, region_id: u64) -> Vec<OperatorDesc> {
+        self.pd_client.pending_operators(region_id)
+    }
+
+    /// Waits until PD schedules an operator of `kind` for the region and
+    /// returns its description, panicking on timeout.
+    pub fn wait_pd_operator(
+        &self,
+        region_id: u64,
+        kind: OperatorKind,
+        timeout: Duration,
+    ) -> OperatorDesc {
+        let start = self.clock.now();
+        loop {
+            if let Some(desc) = self
+                .pending_pd_operators(region_id)
+                .into_iter()
+                .find(|d| d.kind() == kind)
+            {
+                return desc;
+            }
+            if self.clock.now().saturating_sub(start) > timeout {
+                panic!(
+                    "[region {}] pd scheduled no {:?} operator within {:?}",
+                    region_id, kind, timeout
+                );
+            }
+            self.clock.sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Runs `body` and asserts exactly `expected` messages of `msg_type`
+    /// flowed between the two stores, in both directions combined, while it
+    /// ran. The counting filters are installed and removed by the helper,
+    /// so a test can't leak them; note teardown clears every send filter on
+    /// the two involved nodes.
+    pub fn assert_message_count_between<F: FnOnce(&mut Self)>(
+        &mut self,
+        from: u64,
+        to: u64,
+        msg_type: MessageType,
+        expected: u64,
+        body: F,
+    ) {
+        let (out_filter, out_count) =
+            CountingFilter::with_predicate(Arc::new(move |m: &RaftMessage| {
+                m.get_message().get_msg_type() == msg_type
+                    && m.get_to_peer().get_store_id() == to
+            }));
+        let (in_filter, in_count) =
+            CountingFilter::with_predicate(Arc::new(move |m: &RaftMessage| {
+                m.get_message().get_msg_type() == msg_type
+                    && m.get_to_peer().get_store_id() == from
+            }));
+        {
+            let mut sim = self.sim.wl();
+            sim.add_send_filter(from, Box::new(out_filter));
+            sim.add_send_filter(to, Box::new(in_filter));
+        }
+        body(self);
+        {
+            let mut sim = self.sim.wl();
+            sim.clear_send_filters(from);
+            sim.clear_send_filters(to);
+        }
+        let total = out_count.load(Ordering::SeqCst) + in_count.load(Ordering::SeqCst);
+        assert_eq!(
+            total, expected,
+            "expected {} {:?} messages between store {} and store {}, got {}",
+            expected, msg_type, from, to, total
+        );
+    }
+
+    /// Blocks snapshots from being received by the node while all other
+    /// messages still go through, so a leader keeps retrying log
+    /// replication instead of catching the follower up with a snapshot.
+    pub fn block_snapshot_recv(&mut self, node_id: u64) {
+        self.sim.wl().add_recv_filter(
+            node_id,
+            Box::new(DropMessageFilter::new(MessageType::MsgSnapshot)),
+        );
+    }
+
+    /// Lifts `block_snapshot_recv`. Note this clears every recv filter
+    /// installed on the node.
+    pub fn unblock_snapshot_recv(&mut self, node_id: u64) {
+        self.sim.wl().clear_recv_filters(node_id);
+    }
+
     pub fn transfer_leader(&mut self, region_id: u64, leader: metapb::Peer) {
         let epoch = self.get_region_epoch(region_id);
         let transfer_leader = new_admin_request(region_id, &epoch, new_transfer_leader_cmd(leader));
@@ -1229,6 +2111,34 @@ impl<T: Simulator> Cluster<T> {
         }
     }
 
+    /// Waits until the region's leader sits on any store in `stores` and
+    /// returns it, panicking on timeout with the leader observed last. It
+    /// doesn't force a transfer, so it fits cases where several targets are
+    /// acceptable, e.g. after draining one store.
+    pub fn wait_leader_on_one_of(
+        &mut self,
+        region_id: u64,
+        stores: &[u64],
+        timeout: Duration,
+    ) -> metapb::Peer {
+        let timer = Instant::now();
+        let mut cur_leader = None;
+        while timer.saturating_elapsed() < timeout {
+            self.reset_leader_of_region(region_id);
+            cur_leader = self.leader_of_region(region_id);
+            if let Some(ref leader) = cur_leader {
+                if stores.contains(&leader.get_store_id()) {
+                    return leader.clone();
+                }
+            }
+            sleep_ms(20);
+        }
+        panic!(
+            "[region {}] leader not on stores {:?} after {:?}, current leader: {:?}",
+            region_id, stores, timeout, cur_leader
+        );
+    }
+
     pub fn get_snap_dir(&self, node_id: u64) -> String {
         self.sim.rl().get_snap_dir(node_id)
     }
@@ -1406,6 +2316,306 @@ impl<T: Simulator> Cluster<T> {
         }
     }
 
+    /// Proposes merging `source` into `target` and returns once the source
+    /// region has persisted `PeerState::Merging` on its leader store. Epoch
+    /// and leader changes are retried internally.
+    pub fn must_prepare_merge(&mut self, source: u64, target: u64) {
+        let mut try_cnt = 0;
+        loop {
+            let resp = self.try_merge(source, target);
+            if !is_error_response(&resp) {
+                break;
+            }
+            if try_cnt > 250 {
+                panic!(
+                    "{} failed to prepare merge to {}, resp {:?}",
+                    source, target, resp
+                );
+            }
+            try_cnt += 1;
+            sleep_ms(20);
+        }
+        self.must_peer_state(source, PeerState::Merging);
+    }
+
+    /// Proposes a rollback-merge command for `source`, which must already be
+    /// in `PeerState::Merging`, and waits until the source returns to
+    /// `PeerState::Normal`.
+    pub fn must_rollback_merge(&mut self, source: u64) {
+        let mut try_cnt = 0;
+        loop {
+            let leader = self.leader_of_region(source).unwrap();
+            let local_state = self.region_local_state(source, leader.get_store_id());
+            assert_eq!(
+                local_state.get_state(),
+                PeerState::Merging,
+                "{} is not merging: {:?}",
+                source,
+                local_state
+            );
+            // The epoch has been bumped by prepare merge, so take it from the
+            // local state instead of PD, which may not have caught up yet.
+            let req = new_admin_request(
+                source,
+                local_state.get_region().get_region_epoch(),
+                new_rollback_merge(local_state.get_merge_state().get_commit()),
+            );
+            let resp = self
+                .call_command_on_leader(req, Duration::from_secs(5))
+                .unwrap();
+            if !is_error_response(&resp) {
+                break;
+            }
+            if try_cnt > 250 {
+                panic!("{} failed to rollback merge, resp {:?}", source, resp);
+            }
+            try_cnt += 1;
+            sleep_ms(20);
+        }
+        self.must_peer_state(source, PeerState::Normal);
+    }
+
+    /// Adds a learner peer of the region on `store_id` and makes it catch up
+    /// through a snapshot: the store is isolated while the learner is added
+    /// so it can't receive any appended log, then the link is restored and
+    /// the learner has to apply a leader snapshot to reach the leader's
+    /// applied index. Panics if the learner fails to catch up in time.
+    ///
+    /// Note it clears all send filters when restoring the link.
+    pub fn must_add_learner_and_wait_snapshot(&mut self, region_id: u64, store_id: u64) {
+        let leader = self.leader_of_region(region_id).unwrap();
+        assert_ne!(leader.get_store_id(), store_id);
+
+        // Cut the store off first so the learner can only be initialized and
+        // caught up by snapshot once the link is back.
+        self.add_send_filter(IsolationFilterFactory::new(store_id));
+        let learner_id = self.pd_client.alloc_id().unwrap();
+        self.pd_client
+            .must_add_peer(region_id, new_learner_peer(store_id, learner_id));
+
+        self.clear_send_filters();
+        let leader_applied = self
+            .apply_state(region_id, leader.get_store_id())
+            .get_applied_index();
+        for _ in 0..300 {
+            // The learner has no region state until the snapshot is applied,
+            // so read the apply state without unwrapping.
+            let applied = self
+                .get_engine(store_id)
+                .c()
+                .get_msg_cf::<RaftApplyState>(
+                    engine_traits::CF_RAFT,
+                    &keys::apply_state_key(region_id),
+                )
+                .unwrap()
+                .map_or(0, |s| s.get_applied_index());
+            if applied >= leader_applied {
+                return;
+            }
+            sleep_ms(20);
+        }
+        panic!(
+            "learner ({}, {}) of region {} fails to catch up applied index {}",
+            store_id, learner_id, region_id, leader_applied
+        );
+    }
+
+    /// Waits until the region's local state on its leader store reaches the
+    /// expected `PeerState`.
+    pub fn must_peer_state(&mut self, region_id: u64, state: PeerState) {
+        for _ in 0..300 {
+            let leader = self.leader_of_region(region_id).unwrap();
+            let local_state = self.region_local_state(region_id, leader.get_store_id());
+            if local_state.get_state() == state {
+                return;
+            }
+            sleep_ms(20);
+        }
+        panic!("region {} fails to reach peer state {:?}", region_id, state);
+    }
+
+    /// Waits until the region's peers are exactly voters on the `voters`
+    /// stores and learners on the `learners` stores, panicking on timeout
+    /// with the layout seen last. Transient joint states are waited out.
+    pub fn must_have_peers(&mut self, region_id: u64, voters: &[u64], learners: &[u64]) {
+        let mut expected_voters = voters.to_vec();
+        let mut expected_learners = learners.to_vec();
+        expected_voters.sort_unstable();
+        expected_learners.sort_unstable();
+        let mut region = None;
+        for _ in 0..300 {
+            region = block_on(self.pd_client.get_region_by_id(region_id)).unwrap();
+            if let Some(r) = region.as_ref() {
+                let mut cur_voters = Vec::new();
+                let mut cur_learners = Vec::new();
+                let mut in_joint_state = false;
+                for p in r.get_peers() {
+                    match p.get_role() {
+                        PeerRole::Voter => cur_voters.push(p.get_store_id()),
+                        PeerRole::Learner => cur_learners.push(p.get_store_id()),
+                        // Incoming and demoting voters are transient.
+                        _ => in_joint_state = true,
+                    }
+                }
+                cur_voters.sort_unstable();
+                cur_learners.sort_unstable();
+                if !in_joint_state
+                    && cur_voters == expected_voters
+                    && cur_learners == expected_learners
+                {
+                    return;
+                }
+            }
+            sleep_ms(20);
+        }
+        panic!(
+            "[region {}] peers don't match voters {:?} learners {:?}: {:?}",
+            region_id, voters, learners, region
+        );
+    }
+
+    /// Replaces the time source consulted by the polling helpers, e.g.
+    /// `call_command_on_leader` and `wait_pd_operator`, so deterministic
+    /// tests can drive their timeouts with a `ManualClock` instead of
+    /// really sleeping. See `TestClock`.
+    pub fn set_clock(&mut self, clock: Arc<dyn TestClock>) {
+        self.clock = clock;
+    }
+
+    /// Skews the clock observed by the lease logic of the given node by
+    /// `offset`. A zero offset restores real time.
+    ///
+    /// It relies on the lease paths reading the clock through
+    /// `raftstore::store::util::lease_clock_now`, which consults the
+    /// `lease_clock_offset_{node_id}` failpoint, so it only takes effect in
+    /// failpoint builds and supports node ids up to 5.
+    pub fn set_clock_offset(&mut self, node_id: u64, offset: Duration) {
+        let name = format!("lease_clock_offset_{}", node_id);
+        if offset == Duration::from_millis(0) {
+            fail::remove(name);
+        } else {
+            fail::cfg(name, &format!("return({})", offset.as_millis())).unwrap();
+        }
+        self.clock_offsets.insert(node_id, offset);
+    }
+
+    /// Makes the current leader of the region consider its lease expired
+    /// immediately, so the next read falls back to read index instead of
+    /// being served from the lease. Implemented by jumping the leader
+    /// store's injectable clock past the configured max lease, so like
+    /// `set_clock_offset` it only takes effect in failpoint builds.
+    pub fn expire_leader_lease(&mut self, region_id: u64) {
+        let leader = self
+            .leader_of_region(region_id)
+            .expect("leader should be elected");
+        let store_id = leader.get_store_id();
+        let current = self
+            .clock_offsets
+            .get(&store_id)
+            .copied()
+            .unwrap_or_default();
+        // One extra election timeout covers the slack the lease keeps for
+        // clock drift.
+        let slack = self.cfg.raft_store.raft_base_tick_interval.0
+            * self.cfg.raft_store.raft_election_timeout_ticks as u32;
+        let jump = self.cfg.raft_store.raft_store_max_leader_lease.0 + slack;
+        self.set_clock_offset(store_id, current + jump);
+    }
+
+    /// Reads `key` on the region's leader and asserts both that it returns
+    /// `value` and that the read was served through read index rather than
+    /// the leader's lease. Note this clears all send filters when done.
+    pub fn must_read_index_fallback(&mut self, region_id: u64, key: &[u8], value: &[u8]) {
+        let leader = self
+            .leader_of_region(region_id)
+            .expect("leader should be elected");
+        let region = block_on(self.pd_client.get_region_by_id(region_id))
+            .unwrap()
+            .unwrap_or_else(|| panic!("region {} doesn't exist", region_id));
+        let detector = LeaseReadFilter::default();
+        self.add_send_filter(CloneFilterFactory(detector.clone()));
+        must_read_on_peer(self, leader, region, key, value);
+        self.clear_send_filters();
+        assert!(
+            !detector.ctx.rl().is_empty(),
+            "read was served from the lease instead of read index"
+        );
+    }
+
+    /// Returns `(local_lease_reads, read_index_reads)` served by the node
+    /// since the last `reset_read_path_counts`, so tests can tell whether a
+    /// read was served by the leader's lease or fell back to read-index.
+    pub fn read_path_counts(&self, node_id: u64) -> (u64, u64) {
+        raftstore::store::util::read_path::counts(node_id)
+    }
+
+    pub fn reset_read_path_counts(&self, node_id: u64) {
+        raftstore::store::util::read_path::reset(node_id);
+    }
+
+    /// Reads the resolved safe-ts of the region's peer on the store.
+    /// Returns 0 when the peer has no resolved safe-ts yet.
+    pub fn region_safe_ts(&self, region_id: u64, store_id: u64) -> u64 {
+        let meta = self.store_metas[&store_id].lock().unwrap();
+        meta.region_read_progress
+            .get_safe_ts(&region_id)
+            .unwrap_or(0)
+    }
+
+    /// Waits until the peer's resolved safe-ts reaches `ts`, so a stale
+    /// read at `ts` can be issued without racing the resolved-ts advance.
+    pub fn wait_safe_ts_gte(&self, region_id: u64, store_id: u64, ts: u64, timeout: Duration) {
+        let timer = Instant::now();
+        loop {
+            let safe_ts = self.region_safe_ts(region_id, store_id);
+            if safe_ts >= ts {
+                return;
+            }
+            if timer.saturating_elapsed() >= timeout {
+                panic!(
+                    "[region {}] safe ts {} on store {} not >= {} after {:?}",
+                    region_id, safe_ts, store_id, ts, timeout
+                );
+            }
+            sleep_ms(20);
+        }
+    }
+
+    /// Delays every read served by the store by `delay`. Both the local-read
+    /// and the raft read paths go through `ReadExecutor::execute`, which
+    /// consults the `store_read_delay_{node_id}` failpoint, so it only takes
+    /// effect in failpoint builds and supports node ids up to 5. Writes are
+    /// unaffected.
+    pub fn set_read_delay(&mut self, node_id: u64, delay: Duration) {
+        fail::cfg(
+            format!("store_read_delay_{}", node_id),
+            &format!("return({})", delay.as_millis()),
+        )
+        .unwrap();
+    }
+
+    pub fn clear_read_delay(&mut self, node_id: u64) {
+        fail::remove(format!("store_read_delay_{}", node_id));
+    }
+
+    /// Makes apply on `node_id` fail every command of `region_id` committed
+    /// after `after_index`: the dirty write batch is rolled back and the
+    /// proposer gets an error response, without corrupting real data. Apply
+    /// consults the `apply_injected_error_{node_id}` failpoint, so it only
+    /// takes effect in failpoint builds and supports node ids up to 5.
+    pub fn inject_apply_error(&mut self, node_id: u64, region_id: u64, after_index: u64) {
+        fail::cfg(
+            format!("apply_injected_error_{}", node_id),
+            &format!("return({}:{})", region_id, after_index),
+        )
+        .unwrap();
+    }
+
+    /// Resumes normal apply on the node after `inject_apply_error`.
+    pub fn clear_apply_error(&mut self, node_id: u64) {
+        fail::remove(format!("apply_injected_error_{}", node_id));
+    }
+
     /// Make sure region exists on that store.
     pub fn must_region_exist(&mut self, region_id: u64, store_id: u64) {
         let mut try_cnt = 0;
@@ -1483,6 +2693,55 @@ impl<T: Simulator> Cluster<T> {
     pub fn partition(&self, s1: Vec<u64>, s2: Vec<u64>) {
         self.add_send_filter(PartitionFilterFactory::new(s1, s2));
     }
+
+    /// Like `partition`, but the partition heals by itself after `duration`.
+    /// The returned guard can wait for or cancel the scheduled heal, and
+    /// heals on drop so no active partition leaks across tests.
+    pub fn partition_for(&self, s1: Vec<u64>, s2: Vec<u64>, duration: Duration) -> PartitionGuard {
+        let healed = Arc::new(AtomicBool::new(false));
+        let deadline = std::time::Instant::now() + duration;
+        let factory = PartitionFilterFactory::new(s1, s2);
+        let mut sim = self.sim.wl();
+        for node_id in sim.get_node_ids() {
+            for filter in factory.generate(node_id) {
+                sim.add_send_filter(
+                    node_id,
+                    Box::new(HealableFilter::new(filter, healed.clone(), deadline)),
+                );
+            }
+        }
+        PartitionGuard { healed, deadline }
+    }
+}
+
+/// Handle of a partition installed by `Cluster::partition_for`. The
+/// partition heals when the scheduled delay elapses, when the guard is
+/// dropped, or when `cancel` is called, whichever happens first.
+pub struct PartitionGuard {
+    healed: Arc<AtomicBool>,
+    deadline: std::time::Instant,
+}
+
+impl PartitionGuard {
+    /// Heals the partition right away instead of waiting for the schedule.
+    pub fn cancel(self) {
+        // Drop does the work.
+    }
+
+    /// Blocks until the scheduled heal time has passed, so the caller can
+    /// assert the cluster makes progress from then on.
+    pub fn join(self) {
+        let now = std::time::Instant::now();
+        if self.deadline > now {
+            thread::sleep(self.deadline - now);
+        }
+    }
+}
+
+impl Drop for PartitionGuard {
+    fn drop(&mut self) {
+        self.healed.store(true, Ordering::SeqCst);
+    }
 }
 
 impl<T: Simulator> Drop for Cluster<T> {
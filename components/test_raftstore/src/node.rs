@@ -134,6 +134,8 @@ pub struct NodeCluster {
     concurrency_managers: HashMap<u64, ConcurrencyManager>,
     #[allow(clippy::type_complexity)]
     post_create_coprocessor_host: Option<Box<dyn Fn(u64, &mut CoprocessorHost<RocksEngine>)>>,
+    #[allow(clippy::type_complexity)]
+    coprocessor_hooks: HashMap<u64, Vec<Box<dyn Fn(&mut CoprocessorHost<RocksEngine>)>>>,
 }
 
 impl NodeCluster {
@@ -146,6 +148,7 @@ impl NodeCluster {
             simulate_trans: HashMap::default(),
             concurrency_managers: HashMap::default(),
             post_create_coprocessor_host: None,
+            coprocessor_hooks: HashMap::default(),
         }
     }
 }
@@ -244,6 +247,12 @@ impl Simulator for NodeCluster {
             f(node_id, &mut coprocessor_host);
         }
 
+        if let Some(hooks) = self.coprocessor_hooks.get(&node_id) {
+            for hook in hooks {
+                hook(&mut coprocessor_host);
+            }
+        }
+
         let cm = ConcurrencyManager::new(1.into());
         self.concurrency_managers.insert(node_id, cm.clone());
         ReplicaReadLockChecker::new(cm.clone()).register(&mut coprocessor_host);
@@ -437,6 +446,17 @@ impl Simulator for NodeCluster {
         trans.routers.get_mut(&node_id).unwrap().clear_filters();
     }
 
+    fn add_coprocessor_hook(
+        &mut self,
+        node_id: u64,
+        hook: Box<dyn Fn(&mut CoprocessorHost<RocksEngine>)>,
+    ) {
+        self.coprocessor_hooks
+            .entry(node_id)
+            .or_default()
+            .push(hook);
+    }
+
     fn get_router(&self, node_id: u64) -> Option<RaftRouter<RocksEngine, RocksEngine>> {
         self.nodes.get(&node_id).map(|node| node.get_router())
     }
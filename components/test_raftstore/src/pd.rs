@@ -118,6 +118,54 @@ enum Operator {
     },
 }
 
+/// Kinds of operators the test PD can schedule, used to wait for one
+/// without matching its payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperatorKind {
+    AddPeer,
+    RemovePeer,
+    TransferLeader,
+    MergeRegion,
+    SplitRegion,
+    LeaveJoint,
+    JointConfChange,
+}
+
+/// A read-only description of a scheduled operator, exposed to tests so
+/// they can assert what PD proposed instead of only observing its effect.
+#[derive(Clone, Debug)]
+pub enum OperatorDesc {
+    AddPeer(metapb::Peer),
+    RemovePeer(metapb::Peer),
+    TransferLeader(metapb::Peer),
+    MergeRegion {
+        source_region_id: u64,
+        target_region_id: u64,
+    },
+    SplitRegion {
+        keys: Vec<Vec<u8>>,
+    },
+    LeaveJoint,
+    JointConfChange {
+        add_peers: Vec<metapb::Peer>,
+        remove_peers: Vec<metapb::Peer>,
+    },
+}
+
+impl OperatorDesc {
+    pub fn kind(&self) -> OperatorKind {
+        match self {
+            OperatorDesc::AddPeer(_) => OperatorKind::AddPeer,
+            OperatorDesc::RemovePeer(_) => OperatorKind::RemovePeer,
+            OperatorDesc::TransferLeader(_) => OperatorKind::TransferLeader,
+            OperatorDesc::MergeRegion { .. } => OperatorKind::MergeRegion,
+            OperatorDesc::SplitRegion { .. } => OperatorKind::SplitRegion,
+            OperatorDesc::LeaveJoint => OperatorKind::LeaveJoint,
+            OperatorDesc::JointConfChange { .. } => OperatorKind::JointConfChange,
+        }
+    }
+}
+
 fn change_peer(change_type: ConfChangeType, peer: metapb::Peer) -> pdpb::ChangePeer {
     let mut cp = pdpb::ChangePeer::default();
     cp.set_change_type(change_type);
@@ -126,6 +174,37 @@ fn change_peer(change_type: ConfChangeType, peer: metapb::Peer) -> pdpb::ChangeP
 }
 
 impl Operator {
+    fn desc(&self) -> OperatorDesc {
+        match self {
+            Operator::AddPeer { peer, .. } => {
+                let peer = match peer {
+                    Either::Left(p) | Either::Right(p) => p.clone(),
+                };
+                OperatorDesc::AddPeer(peer)
+            }
+            Operator::RemovePeer { peer, .. } => OperatorDesc::RemovePeer(peer.clone()),
+            Operator::TransferLeader { peer, .. } => OperatorDesc::TransferLeader(peer.clone()),
+            Operator::MergeRegion {
+                source_region_id,
+                target_region_id,
+                ..
+            } => OperatorDesc::MergeRegion {
+                source_region_id: *source_region_id,
+                target_region_id: *target_region_id,
+            },
+            Operator::SplitRegion { keys, .. } => OperatorDesc::SplitRegion { keys: keys.clone() },
+            Operator::LeaveJoint { .. } => OperatorDesc::LeaveJoint,
+            Operator::JointConfChange {
+                add_peers,
+                remove_peers,
+                ..
+            } => OperatorDesc::JointConfChange {
+                add_peers: add_peers.clone(),
+                remove_peers: remove_peers.clone(),
+            },
+        }
+    }
+
     fn make_region_heartbeat_response(
         &self,
         region_id: u64,
@@ -797,6 +876,19 @@ impl TestPdClient {
         self.cluster.rl().get_regions_number()
     }
 
+    /// Returns descriptions of the operators currently scheduled for the
+    /// region. The test PD keeps at most one on-going operator per region,
+    /// so the result holds zero or one entry.
+    pub fn pending_operators(&self, region_id: u64) -> Vec<OperatorDesc> {
+        self.cluster
+            .rl()
+            .operators
+            .get(&region_id)
+            .map(Operator::desc)
+            .into_iter()
+            .collect()
+    }
+
     pub fn disable_default_operator(&self) {
         self.cluster.wl().enable_peer_count_check = false;
     }
@@ -43,7 +43,7 @@ use tikv::coprocessor;
 use tikv::coprocessor_v2;
 use tikv::import::{ImportSSTService, SSTImporter};
 use tikv::read_pool::ReadPool;
-use tikv::server::gc_worker::GcWorker;
+use tikv::server::gc_worker::{GcTask, GcWorker};
 use tikv::server::lock_manager::LockManager;
 use tikv::server::resolve::{self, StoreAddrResolver};
 use tikv::server::service::DebugService;
@@ -59,7 +59,7 @@ use tikv::{
 };
 use tikv_util::config::VersionTrack;
 use tikv_util::time::ThreadReadId;
-use tikv_util::worker::{Builder as WorkerBuilder, FutureWorker, LazyWorker};
+use tikv_util::worker::{Builder as WorkerBuilder, FutureScheduler, FutureWorker, LazyWorker};
 use tikv_util::HandyRwLock;
 use txn_types::TxnExtraScheduler;
 
@@ -594,9 +594,30 @@ impl Simulator for ServerCluster {
             .clear_filters();
     }
 
+    fn add_coprocessor_hook(
+        &mut self,
+        node_id: u64,
+        hook: Box<dyn Fn(&mut CoprocessorHost<RocksEngine>)>,
+    ) {
+        self.coprocessor_hooks
+            .entry(node_id)
+            .or_default()
+            .push(hook);
+    }
+
     fn get_router(&self, node_id: u64) -> Option<RaftRouter<RocksEngine, RocksEngine>> {
         self.metas.get(&node_id).map(|m| m.raw_router.clone())
     }
+
+    fn get_gc_safe_point(&self, node_id: u64) -> Option<u64> {
+        self.metas
+            .get(&node_id)
+            .map(|m| m.gc_worker.applied_safe_point())
+    }
+
+    fn get_gc_scheduler(&self, node_id: u64) -> Option<FutureScheduler<GcTask<RocksEngine>>> {
+        self.metas.get(&node_id).map(|m| m.gc_worker.scheduler())
+    }
 }
 
 pub fn new_server_cluster(id: u64, count: usize) -> Cluster<ServerCluster> {
@@ -102,6 +102,76 @@ impl Filter for MessageTypeNotifier {
     }
 }
 
+/// Counts every message matching a predicate without dropping anything.
+///
+/// Useful for assertions like "exactly 3 snapshots were sent":
+///
+/// ```ignore
+/// let (factory, sent_snapshots) = CountingFilterFactory::new(MessageType::MsgSnapshot);
+/// cluster.add_send_filter(factory);
+/// // ... trigger snapshots ...
+/// assert_eq!(sent_snapshots.load(Ordering::SeqCst), 3);
+/// ```
+#[derive(Clone)]
+pub struct CountingFilter {
+    predicate: Arc<dyn Fn(&RaftMessage) -> bool + Send + Sync>,
+    counter: Arc<AtomicU64>,
+}
+
+impl CountingFilter {
+    /// Creates a filter counting messages of `msg_type` and the shared
+    /// counter handle.
+    pub fn new(msg_type: MessageType) -> (CountingFilter, Arc<AtomicU64>) {
+        Self::with_predicate(Arc::new(move |m: &RaftMessage| {
+            m.get_message().get_msg_type() == msg_type
+        }))
+    }
+
+    /// Creates a filter counting messages matching an arbitrary predicate.
+    pub fn with_predicate(
+        predicate: Arc<dyn Fn(&RaftMessage) -> bool + Send + Sync>,
+    ) -> (CountingFilter, Arc<AtomicU64>) {
+        let counter = Arc::new(AtomicU64::new(0));
+        (
+            CountingFilter {
+                predicate,
+                counter: counter.clone(),
+            },
+            counter,
+        )
+    }
+}
+
+impl Filter for CountingFilter {
+    fn before(&self, msgs: &mut Vec<RaftMessage>) -> Result<()> {
+        for msg in msgs.iter() {
+            if (self.predicate)(msg) {
+                self.counter.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Installs a `CountingFilter` on every node, all sharing one counter.
+#[derive(Clone)]
+pub struct CountingFilterFactory {
+    filter: CountingFilter,
+}
+
+impl CountingFilterFactory {
+    pub fn new(msg_type: MessageType) -> (CountingFilterFactory, Arc<AtomicU64>) {
+        let (filter, counter) = CountingFilter::new(msg_type);
+        (CountingFilterFactory { filter }, counter)
+    }
+}
+
+impl FilterFactory for CountingFilterFactory {
+    fn generate(&self, _: u64) -> Vec<Box<dyn Filter>> {
+        vec![Box::new(self.filter.clone())]
+    }
+}
+
 #[derive(Clone)]
 pub struct DropPacketFilter {
     rate: u32,
@@ -120,6 +190,88 @@ impl Filter for DropPacketFilter {
     }
 }
 
+/// Buffers messages per (from store, to store) pair and releases them in a
+/// shuffled order once `capacity` messages have accumulated for that pair.
+/// `clear` marks all buffers for release, so the next send flushes them.
+/// Every buffered message is delivered exactly once (as long as the filter
+/// stays installed), only the order is perturbed. The RNG is seeded, so a
+/// fixed seed reproduces the same reordering.
+pub struct ReorderFilter {
+    capacity: usize,
+    rng: Mutex<rand::rngs::StdRng>,
+    buffered: Mutex<HashMap<(u64, u64), Vec<RaftMessage>>>,
+    flush: AtomicBool,
+}
+
+impl ReorderFilter {
+    pub fn new(capacity: usize, seed: u64) -> ReorderFilter {
+        assert!(capacity > 0);
+        ReorderFilter {
+            capacity,
+            rng: Mutex::new(rand::SeedableRng::seed_from_u64(seed)),
+            buffered: Mutex::new(HashMap::default()),
+            flush: AtomicBool::new(false),
+        }
+    }
+
+    /// Releases all buffered messages (in shuffled order) into the next send.
+    pub fn clear(&self) {
+        self.flush.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Filter for ReorderFilter {
+    fn before(&self, msgs: &mut Vec<RaftMessage>) -> Result<()> {
+        use rand::seq::SliceRandom;
+
+        let mut buffered = self.buffered.lock().unwrap();
+        let mut rng = self.rng.lock().unwrap();
+        let mut to_send = vec![];
+        for m in msgs.drain(..) {
+            let key = (
+                m.get_from_peer().get_store_id(),
+                m.get_to_peer().get_store_id(),
+            );
+            let buf = buffered.entry(key).or_insert_with(Vec::new);
+            buf.push(m);
+            if buf.len() >= self.capacity {
+                buf.shuffle(&mut *rng);
+                to_send.append(buf);
+            }
+        }
+        if self.flush.swap(false, Ordering::SeqCst) {
+            for buf in buffered.values_mut() {
+                buf.shuffle(&mut *rng);
+                to_send.append(buf);
+            }
+        }
+        msgs.extend(to_send);
+        Ok(())
+    }
+}
+
+pub struct ReorderFilterFactory {
+    capacity: usize,
+    seed: u64,
+}
+
+impl ReorderFilterFactory {
+    pub fn new(capacity: usize, seed: u64) -> ReorderFilterFactory {
+        ReorderFilterFactory { capacity, seed }
+    }
+}
+
+impl FilterFactory for ReorderFilterFactory {
+    fn generate(&self, node_id: u64) -> Vec<Box<dyn Filter>> {
+        // Derive a per-store seed so every store reorders deterministically
+        // but not identically.
+        vec![Box::new(ReorderFilter::new(
+            self.capacity,
+            self.seed.wrapping_add(node_id),
+        ))]
+    }
+}
+
 #[derive(Clone)]
 pub struct DelayFilter {
     duration: time::Duration,
@@ -289,6 +441,56 @@ impl Filter for PartitionFilter {
     }
 }
 
+/// Wraps a filter and applies it only until it heals: either the shared
+/// `healed` flag is set or `deadline` passes. From then on messages go
+/// through untouched, as if the filter had been cleared.
+pub struct HealableFilter {
+    inner: Box<dyn Filter>,
+    healed: Arc<AtomicBool>,
+    deadline: time::Instant,
+}
+
+impl HealableFilter {
+    pub fn new(
+        inner: Box<dyn Filter>,
+        healed: Arc<AtomicBool>,
+        deadline: time::Instant,
+    ) -> HealableFilter {
+        HealableFilter {
+            inner,
+            healed,
+            deadline,
+        }
+    }
+
+    fn is_healed(&self) -> bool {
+        if self.healed.load(Ordering::SeqCst) {
+            return true;
+        }
+        if time::Instant::now() >= self.deadline {
+            self.healed.store(true, Ordering::SeqCst);
+            return true;
+        }
+        false
+    }
+}
+
+impl Filter for HealableFilter {
+    fn before(&self, msgs: &mut Vec<RaftMessage>) -> Result<()> {
+        if self.is_healed() {
+            return Ok(());
+        }
+        self.inner.before(msgs)
+    }
+
+    fn after(&self, res: Result<()>) -> Result<()> {
+        if self.is_healed() {
+            return res;
+        }
+        self.inner.after(res)
+    }
+}
+
 pub struct PartitionFilterFactory {
     s1: Vec<u64>,
     s2: Vec<u64>,
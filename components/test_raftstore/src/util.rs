@@ -2,6 +2,7 @@
 
 use std::fmt::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::Duration;
 use std::{thread, u64};
@@ -18,8 +19,8 @@ use kvproto::pdpb::{
 };
 use kvproto::raft_cmdpb::{AdminCmdType, CmdType, StatusCmdType};
 use kvproto::raft_cmdpb::{
-    AdminRequest, ChangePeerRequest, ChangePeerV2Request, RaftCmdRequest, RaftCmdResponse, Request,
-    StatusRequest,
+    AdminRequest, AdminResponse, ChangePeerRequest, ChangePeerV2Request, RaftCmdRequest,
+    RaftCmdResponse, Request, StatusRequest,
 };
 use kvproto::raft_serverpb::{PeerState, RaftLocalState, RegionLocalState};
 use kvproto::tikvpb::TikvClient;
@@ -38,6 +39,9 @@ use engine_rocks::{Compat, RocksEngine, RocksSnapshot};
 use engine_traits::{Engines, Iterable, Peekable};
 use file_system::IORateLimiter;
 use futures::executor::block_on;
+use raftstore::coprocessor::{
+    AdminObserver, Cmd, Coprocessor, ObserverContext, QueryObserver, Result as CopResult,
+};
 use raftstore::store::fsm::RaftRouter;
 use raftstore::store::*;
 use raftstore::Result;
@@ -52,7 +56,7 @@ use super::*;
 use crate::pd_client::PdClient;
 use engine_traits::{ALL_CFS, CF_DEFAULT, CF_RAFT};
 pub use raftstore::store::util::{find_peer, new_learner_peer, new_peer};
-use tikv_util::time::ThreadReadId;
+use tikv_util::time::{Instant, ThreadReadId};
 
 pub fn must_get(engine: &Arc<DB>, cf: &str, key: &[u8], value: Option<&[u8]>) {
     for _ in 1..300 {
@@ -300,6 +304,13 @@ pub fn new_prepare_merge(target_region: metapb::Region) -> AdminRequest {
     cmd
 }
 
+pub fn new_rollback_merge(commit: u64) -> AdminRequest {
+    let mut cmd = AdminRequest::default();
+    cmd.set_cmd_type(AdminCmdType::RollbackMerge);
+    cmd.mut_rollback_merge().set_commit(commit);
+    cmd
+}
+
 pub fn new_store(store_id: u64, addr: String) -> metapb::Store {
     let mut store = metapb::Store::default();
     store.set_id(store_id);
@@ -1072,3 +1083,124 @@ pub fn must_remove_lock_observer(client: &TikvClient, max_ts: u64) {
     let resp = remove_lock_observer(client, max_ts);
     assert!(resp.get_error().is_empty(), "{:?}", resp.get_error());
 }
+
+/// Per-hook invocation counters recorded by the observer installed with
+/// `Cluster::install_counting_observer`.
+#[derive(Default)]
+pub struct ObserverCounts {
+    pub pre_propose_admin: AtomicUsize,
+    pub pre_apply_admin: AtomicUsize,
+    pub post_apply_admin: AtomicUsize,
+    pub pre_propose_query: AtomicUsize,
+    pub pre_apply_query: AtomicUsize,
+    pub post_apply_query: AtomicUsize,
+}
+
+#[derive(Clone)]
+pub struct CountingObserver {
+    counts: Arc<ObserverCounts>,
+}
+
+impl CountingObserver {
+    pub fn new(counts: Arc<ObserverCounts>) -> CountingObserver {
+        CountingObserver { counts }
+    }
+}
+
+impl Coprocessor for CountingObserver {}
+
+impl AdminObserver for CountingObserver {
+    fn pre_propose_admin(
+        &self,
+        _: &mut ObserverContext<'_>,
+        _: &mut AdminRequest,
+    ) -> CopResult<()> {
+        self.counts.pre_propose_admin.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn pre_apply_admin(&self, _: &mut ObserverContext<'_>, _: &AdminRequest) {
+        self.counts.pre_apply_admin.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn post_apply_admin(&self, _: &mut ObserverContext<'_>, _: &AdminResponse) {
+        self.counts.post_apply_admin.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl QueryObserver for CountingObserver {
+    fn pre_propose_query(
+        &self,
+        _: &mut ObserverContext<'_>,
+        _: &mut Vec<Request>,
+    ) -> CopResult<()> {
+        self.counts.pre_propose_query.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn pre_apply_query(&self, _: &mut ObserverContext<'_>, _: &[Request]) {
+        self.counts.pre_apply_query.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn post_apply_query(&self, _: &mut ObserverContext<'_>, _: &Cmd) {
+        self.counts.post_apply_query.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Time source consulted by `Cluster` polling helpers, so deterministic
+/// tests can advance time manually instead of being bound to the wall
+/// clock. See `Cluster::set_clock`.
+pub trait TestClock: Send + Sync {
+    /// A monotonic reading of the clock.
+    fn now(&self) -> Duration;
+
+    /// Waits for the duration, either by really sleeping or by advancing
+    /// a virtual clock.
+    fn sleep(&self, dur: Duration);
+}
+
+/// The default implementation backed by the wall clock.
+pub struct RealClock {
+    start: Instant,
+}
+
+impl Default for RealClock {
+    fn default() -> RealClock {
+        RealClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl TestClock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.saturating_elapsed()
+    }
+
+    fn sleep(&self, dur: Duration) {
+        thread::sleep(dur);
+    }
+}
+
+/// A manually advanced clock: `sleep` advances it immediately and returns,
+/// and tests can move it forward from outside with `advance`.
+#[derive(Default)]
+pub struct ManualClock {
+    nanos: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn advance(&self, dur: Duration) {
+        self.nanos.fetch_add(dur.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl TestClock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    fn sleep(&self, dur: Duration) {
+        self.advance(dur);
+    }
+}
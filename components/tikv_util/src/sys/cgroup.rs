@@ -11,8 +11,14 @@ use thiserror::Error;
 const CGROUP_PATH: &str = "/proc/self/cgroup";
 const CGROUP_MOUNTINFO: &str = "/proc/self/mountinfo";
 const CGROUP_FSTYPE: &str = "cgroup";
+const CGROUP2_FSTYPE: &str = "cgroup2";
+// Key used to store the cgroup v2 unified hierarchy in `CGroupSys::cgroups`.
+// It can't clash with v1 subsystem names.
+const CGROUP2_UNIFIED: &str = "cgroup2";
 const MEM_SUBSYS: &str = "memory";
 const MEM_LIMIT_IN_BYTES: &str = "memory.limit_in_bytes";
+const MEM_MAX_V2: &str = "memory.max";
+const MEM_CURRENT_V2: &str = "memory.current";
 const CPU_SUBSYS: &str = "cpu";
 const CPU_QUOTA: &str = "cpu.cfs_quota_us";
 const CPU_PERIOD: &str = "cpu.cfs_period_us";
@@ -168,6 +174,16 @@ pub fn parse_subsys_from_line(line: &str) -> Result<CGroupSubsys, Error> {
     })
 }
 
+/// Parses the content of a cgroup v2 memory file. `max` means no limit and
+/// is reported as -1, matching the v1 convention.
+pub fn parse_v2_memory_num(content: &str) -> Result<i64, Box<dyn error::Error>> {
+    let content = content.trim();
+    if content == "max" {
+        return Ok(-1);
+    }
+    Ok(content.parse::<i64>()?)
+}
+
 pub fn file_scanner<F>(path: &str, mut f: F) -> std::io::Result<()>
 where
     F: FnMut(&str),
@@ -204,6 +220,12 @@ impl CGroup {
         Ok(self.read_line(param)?.trim().parse::<i64>()?)
     }
 
+    /// Reads a cgroup v2 memory value like `memory.max` or `memory.current`.
+    /// `max` stands for no limit and is reported as -1.
+    pub fn read_v2_memory_num(&self, param: &str) -> Result<i64, Box<dyn error::Error>> {
+        parse_v2_memory_num(&self.read_line(param)?)
+    }
+
     pub fn read_cpuset(&self) -> Result<HashSet<usize>, Box<dyn error::Error>> {
         let line = self.read_line(CPUSET_CPUS)?;
         let content = line.trim();
@@ -257,6 +279,14 @@ impl CGroupSys {
                             }
                         }
                     }
+                } else if mp.fs_type == CGROUP2_FSTYPE {
+                    // The v2 unified hierarchy shows up in /proc/self/cgroup
+                    // as `0::<path>`, i.e. with an empty subsystem list.
+                    if let Some(sub) = subsystems.get("") {
+                        if let Ok(path) = mp.translate(&sub.name) {
+                            cgroups.insert(CGROUP2_UNIFIED.to_string(), CGroup::new(path));
+                        }
+                    }
                 }
             }
         });
@@ -291,17 +321,32 @@ impl CGroupSys {
                 return limits_in_bytes;
             }
         }
+        if let Some(unified) = self.cgroups.get(CGROUP2_UNIFIED) {
+            if let Ok(max) = unified.read_v2_memory_num(MEM_MAX_V2) {
+                return max;
+            }
+        }
 
         // -1 means no limit.
         -1
     }
+
+    /// Current memory usage of the cgroup in bytes, or -1 if unavailable.
+    pub fn memory_current_in_bytes(&self) -> i64 {
+        if let Some(unified) = self.cgroups.get(CGROUP2_UNIFIED) {
+            if let Ok(current) = unified.read_v2_memory_num(MEM_CURRENT_V2) {
+                return current;
+            }
+        }
+        -1
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        parse_mount_point_from_line, parse_subsys_from_line, CGroup, CGroupSubsys, CGroupSys,
-        MountPoint, CPUSET_CPUS,
+        parse_mount_point_from_line, parse_subsys_from_line, parse_v2_memory_num, CGroup,
+        CGroupSubsys, CGroupSys, MountPoint, CPUSET_CPUS,
     };
     use std::collections::HashMap;
     use std::fs::File;
@@ -478,6 +523,56 @@ mod tests {
         assert_eq!(6, cgroup.read_cpuset().unwrap().len());
     }
 
+    #[test]
+    fn test_parse_v2_memory_num() {
+        assert_eq!(parse_v2_memory_num("max\n").unwrap(), -1);
+        assert_eq!(parse_v2_memory_num("9223372036854771712\n").unwrap(), 9223372036854771712);
+        assert_eq!(parse_v2_memory_num("123").unwrap(), 123);
+        assert!(parse_v2_memory_num("abc").is_err());
+    }
+
+    #[test]
+    fn test_cgroup_sys_v2() {
+        let temp_dir = TempDir::new().unwrap();
+        let path1 = temp_dir.path().join("cgroup");
+        let mut f1 = File::create(path1.clone()).unwrap();
+        f1.write_all(b"0::/\n").unwrap();
+        f1.sync_all().unwrap();
+
+        // Mock the unified hierarchy mounted on a temp dir so memory files
+        // can be read back.
+        let mount_point = temp_dir.path().join("unified");
+        std::fs::create_dir(&mount_point).unwrap();
+        let path2 = temp_dir.path().join("mountinfo");
+        let mut f2 = File::create(path2.clone()).unwrap();
+        f2.write_all(
+            format!(
+                "30 23 0:26 / {} rw,nosuid,nodev,noexec,relatime shared:4 - cgroup2 cgroup2 rw\n",
+                mount_point.to_str().unwrap()
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        f2.sync_all().unwrap();
+
+        let mut f3 = File::create(mount_point.join("memory.max")).unwrap();
+        f3.write_all(b"1073741824\n").unwrap();
+        f3.sync_all().unwrap();
+        let mut f4 = File::create(mount_point.join("memory.current")).unwrap();
+        f4.write_all(b"536870912\n").unwrap();
+        f4.sync_all().unwrap();
+
+        let cgroup = CGroupSys::new(path1.to_str().unwrap(), path2.to_str().unwrap());
+        assert_eq!(cgroup.memory_limit_in_bytes(), 1073741824);
+        assert_eq!(cgroup.memory_current_in_bytes(), 536870912);
+
+        // `max` means no limit.
+        let mut f5 = File::create(mount_point.join("memory.max")).unwrap();
+        f5.write_all(b"max\n").unwrap();
+        f5.sync_all().unwrap();
+        assert_eq!(cgroup.memory_limit_in_bytes(), -1);
+    }
+
     #[test]
     fn test_cgroup_sys() {
         let temp_dir = TempDir::new().unwrap();
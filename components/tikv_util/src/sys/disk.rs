@@ -1,7 +1,38 @@
 // Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
 use fail::fail_point;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
 static DISK_FULL: AtomicBool = AtomicBool::new(false);
+static DISK_RESERVED_SPACE: AtomicU64 = AtomicU64::new(0);
+static DISK_CAPACITY: AtomicU64 = AtomicU64::new(0);
+static DISK_AVAILABLE: AtomicU64 = AtomicU64::new(0);
+
+/// Update the reserved space size in bytes. It's used by the disk space
+/// checker and can be refreshed when `storage.reserve-space` is changed
+/// online.
+pub fn set_disk_reserved_space(v: u64) {
+    DISK_RESERVED_SPACE.store(v, Ordering::Release)
+}
+
+pub fn get_disk_reserved_space() -> u64 {
+    DISK_RESERVED_SPACE.load(Ordering::Acquire)
+}
+
+/// Publish the latest capacity and available bytes of the store disk.
+/// Refreshed by the periodic storage stats task, so readers (e.g. the
+/// status server) never touch the filesystem themselves.
+pub fn set_disk_stats(capacity: u64, available: u64) {
+    DISK_CAPACITY.store(capacity, Ordering::Release);
+    DISK_AVAILABLE.store(available, Ordering::Release);
+}
+
+pub fn get_disk_capacity() -> u64 {
+    DISK_CAPACITY.load(Ordering::Acquire)
+}
+
+pub fn get_disk_available() -> u64 {
+    DISK_AVAILABLE.load(Ordering::Acquire)
+}
 
 pub fn set_disk_full() {
     DISK_FULL.store(true, Ordering::Release);
@@ -15,6 +46,19 @@ pub fn is_disk_full() -> bool {
     DISK_FULL.load(Ordering::Acquire)
 }
 
+lazy_static::lazy_static! {
+    static ref TEST_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Serializes tests that mutate the process-global disk state. Tests in one
+/// binary run in parallel, so every test touching these statics must hold
+/// the returned guard for its whole duration or it races with the others.
+pub fn test_exclusive_lock() -> MutexGuard<'static, ()> {
+    // A panicking test poisons the lock; the state is reset by the next
+    // holder anyway.
+    TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 pub fn disk_full_precheck(_store_id: u64) -> bool {
     fail_point!("disk_full_peer_1", _store_id == 1, |_| true);
     fail_point!("disk_full_peer_2", _store_id == 2, |_| true);
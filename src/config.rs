@@ -1240,6 +1240,15 @@ pub struct RaftDbConfig {
     pub allow_concurrent_memtable_write: bool,
     pub bytes_per_sync: ReadableSize,
     pub wal_bytes_per_sync: ReadableSize,
+    /// Whether to store a CRC32 with every raft log entry and verify it on
+    /// read. It changes the value format of raft logs, so it must not be
+    /// toggled once the raft db holds data.
+    #[online_config(skip)]
+    pub enable_log_checksum: bool,
+    /// How many pending deletes the raft log gc accumulates before flushing
+    /// its write batch. Lower it to bound individual gc writes on slow disks.
+    #[online_config(skip)]
+    pub gc_batch_keys: usize,
     #[online_config(submodule)]
     pub defaultcf: RaftDefaultCfConfig,
     #[online_config(skip)]
@@ -1280,6 +1289,8 @@ impl Default for RaftDbConfig {
             allow_concurrent_memtable_write: true,
             bytes_per_sync: ReadableSize::mb(1),
             wal_bytes_per_sync: ReadableSize::kb(512),
+            enable_log_checksum: false,
+            gc_batch_keys: 256,
             defaultcf: RaftDefaultCfConfig::default(),
             titan: titan_config,
         }
@@ -1345,6 +1356,9 @@ impl RaftDbConfig {
                 );
             }
         }
+        if self.gc_batch_keys == 0 {
+            return Err("raftdb: gc_batch_keys must be greater than 0".into());
+        }
         Ok(())
     }
 }
@@ -3205,11 +3219,13 @@ mod tests {
     use crate::storage::config::StorageConfigManger;
     use engine_rocks::raw_util::new_engine_opt;
     use engine_traits::DBOptions as DBOptionsTrait;
+    use file_system::SPACE_PLACEHOLDER_FILE;
     use raft_log_engine::RecoveryMode;
     use raftstore::coprocessor::region_info_accessor::MockRegionInfoProvider;
     use slog::Level;
     use std::sync::Arc;
     use std::time::Duration;
+    use tikv_util::sys::disk;
     use tikv_util::worker::{dummy_scheduler, ReceiverWrapper};
 
     #[test]
@@ -3502,9 +3518,24 @@ mod tests {
             Box::new(DBConfigManger::new(engine.clone(), DBType::Kv, shared)),
         );
         let (scheduler, receiver) = dummy_scheduler();
+        let (data_dir, capacity, skip_placeholder_file) = {
+            let cfg = cfg_controller.get_current();
+            (
+                cfg.storage.data_dir.clone(),
+                cfg.raft_store.capacity,
+                cfg.storage.skip_placeholder_file,
+            )
+        };
         cfg_controller.register(
             Module::Storage,
-            Box::new(StorageConfigManger::new(engine.clone(), shared, scheduler)),
+            Box::new(StorageConfigManger::new(
+                engine.clone(),
+                shared,
+                scheduler,
+                data_dir,
+                capacity,
+                skip_placeholder_file,
+            )),
         );
         (engine, cfg_controller, receiver)
     }
@@ -3746,6 +3777,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_change_reserve_space() {
+        let (mut cfg, _dir) = TiKvConfig::with_tmp().unwrap();
+        // Keep the capacity small so that the placeholder file stays cheap.
+        cfg.raft_store.capacity = ReadableSize::mb(10);
+        cfg.validate().unwrap();
+        let placeholder_path = Path::new(&cfg.storage.data_dir).join(SPACE_PLACEHOLDER_FILE);
+        let (_, cfg_controller, _rx) = new_engines(cfg);
+
+        let placeholder_size = |path: &Path| std::fs::metadata(path).unwrap().len();
+
+        // Increase the reserved space, the placeholder is max(5% capacity, config).
+        cfg_controller
+            .update_config("storage.reserve-space", "1MB")
+            .unwrap();
+        assert_eq!(disk::get_disk_reserved_space(), ReadableSize::mb(1).0);
+        assert_eq!(placeholder_size(&placeholder_path), ReadableSize::mb(1).0);
+
+        // Decrease it, 5% of the capacity takes over.
+        cfg_controller
+            .update_config("storage.reserve-space", "100KB")
+            .unwrap();
+        assert_eq!(disk::get_disk_reserved_space(), ReadableSize::kb(512).0);
+        assert_eq!(placeholder_size(&placeholder_path), ReadableSize::kb(512).0);
+
+        // Zero disables the reservation and removes the placeholder.
+        cfg_controller
+            .update_config("storage.reserve-space", "0KB")
+            .unwrap();
+        assert_eq!(disk::get_disk_reserved_space(), 0);
+        assert!(!placeholder_path.exists());
+    }
+
+    #[test]
+    fn test_skip_placeholder_file() {
+        let (mut cfg, _dir) = TiKvConfig::with_tmp().unwrap();
+        cfg.raft_store.capacity = ReadableSize::mb(10);
+        cfg.storage.skip_placeholder_file = true;
+        cfg.validate().unwrap();
+        let placeholder_path = Path::new(&cfg.storage.data_dir).join(SPACE_PLACEHOLDER_FILE);
+        let (_, cfg_controller, _rx) = new_engines(cfg);
+
+        // The reservation is still accounted, but no placeholder file is
+        // allocated.
+        cfg_controller
+            .update_config("storage.reserve-space", "1MB")
+            .unwrap();
+        assert!(!placeholder_path.exists());
+    }
+
     #[test]
     fn test_compatible_adjust_validate_equal() {
         // After calling many time of `compatible_adjust` and `validate` should has
@@ -132,6 +132,20 @@ pub struct Config {
     pub end_point_max_concurrency: usize,
     pub snap_max_write_bytes_per_sec: ReadableSize,
     pub snap_max_total_size: ReadableSize,
+    /// Incoming snapshots are rejected with a retriable error while the
+    /// store's available disk space is below this size, so a nearly full
+    /// store isn't tipped over by applying them. 0 means snapshots are only
+    /// rejected once the disk is already considered full.
+    pub snap_recv_min_available_size: ReadableSize,
+    /// Startup reports the measured clock offset against PD's TSO time and
+    /// complains when it exceeds this threshold; leases and TSO ordering
+    /// assume bounded drift.
+    #[online_config(skip)]
+    pub clock_drift_threshold: ReadableDuration,
+    /// Whether exceeding `clock_drift_threshold` aborts startup instead of
+    /// only logging a warning.
+    #[online_config(skip)]
+    pub panic_on_clock_drift: bool,
     #[online_config(skip)]
     pub stats_concurrency: usize,
     #[online_config(skip)]
@@ -220,6 +234,9 @@ impl Default for Config {
             end_point_max_concurrency: cmp::max(cpu_num as usize, MIN_ENDPOINT_MAX_CONCURRENCY),
             snap_max_write_bytes_per_sec: ReadableSize(DEFAULT_SNAP_MAX_BYTES_PER_SEC),
             snap_max_total_size: ReadableSize(0),
+            snap_recv_min_available_size: ReadableSize(0),
+            clock_drift_threshold: ReadableDuration::millis(500),
+            panic_on_clock_drift: false,
             stats_concurrency: 1,
             // 300 means gRPC threads are under heavy load if their total CPU usage
             // is greater than 300%.
@@ -21,6 +21,9 @@ pub struct GcConfig {
     /// By default compaction_filter can only works if `cluster_version` is greater than 5.0.0.
     /// Change `compaction_filter_skip_version_check` can enable it by force.
     pub compaction_filter_skip_version_check: bool,
+    /// Limits how many region-range GC compactions may run at the same time,
+    /// so bulk cleanup can't saturate IO. 0 means no limit.
+    pub max_concurrent_range_compactions: usize,
 }
 
 impl Default for GcConfig {
@@ -31,6 +34,7 @@ impl Default for GcConfig {
             max_write_bytes_per_sec: ReadableSize(DEFAULT_GC_MAX_WRITE_BYTES_PER_SEC),
             enable_compaction_filter: true,
             compaction_filter_skip_version_check: false,
+            max_concurrent_range_compactions: 0,
         }
     }
 }
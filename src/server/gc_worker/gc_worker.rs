@@ -5,7 +5,7 @@ use std::fmt::{self, Display, Formatter};
 use std::iter::Peekable;
 use std::mem;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use std::vec::IntoIter;
 
 use concurrency_manager::ConcurrencyManager;
@@ -169,6 +169,62 @@ where
     }
 }
 
+/// Semaphore limiting how many region-range GC compactions run at once, so
+/// bulk cleanup can't saturate IO. The limit can be changed online; 0 means
+/// no limit. In-flight and queued counts are exported through
+/// `GC_RANGE_COMPACTIONS_INFLIGHT` and `GC_RANGE_COMPACTIONS_QUEUED`.
+struct RangeCompactionThrottle {
+    inflight: Mutex<usize>,
+    cond: Condvar,
+    limit: AtomicUsize,
+}
+
+impl RangeCompactionThrottle {
+    fn new(limit: usize) -> Self {
+        RangeCompactionThrottle {
+            inflight: Mutex::new(0),
+            cond: Condvar::new(),
+            limit: AtomicUsize::new(limit),
+        }
+    }
+
+    fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+        self.cond.notify_all();
+    }
+
+    /// Blocks until a permit is available, then holds it until the returned
+    /// guard is dropped.
+    fn acquire(&self) -> RangeCompactionPermit<'_> {
+        GC_RANGE_COMPACTIONS_QUEUED.inc();
+        let mut inflight = self.inflight.lock().unwrap();
+        loop {
+            let limit = self.limit.load(Ordering::Relaxed);
+            if limit == 0 || *inflight < limit {
+                break;
+            }
+            inflight = self.cond.wait(inflight).unwrap();
+        }
+        *inflight += 1;
+        GC_RANGE_COMPACTIONS_QUEUED.dec();
+        GC_RANGE_COMPACTIONS_INFLIGHT.inc();
+        RangeCompactionPermit { throttle: self }
+    }
+}
+
+struct RangeCompactionPermit<'a> {
+    throttle: &'a RangeCompactionThrottle,
+}
+
+impl Drop for RangeCompactionPermit<'_> {
+    fn drop(&mut self) {
+        let mut inflight = self.throttle.inflight.lock().unwrap();
+        *inflight -= 1;
+        GC_RANGE_COMPACTIONS_INFLIGHT.dec();
+        self.throttle.cond.notify_one();
+    }
+}
+
 /// Used to perform GC operations on the engine.
 struct GcRunner<E, RR>
 where
@@ -182,6 +238,13 @@ where
     /// Used to limit the write flow of GC.
     limiter: Limiter,
 
+    /// Limits concurrent region-range GC compactions.
+    range_compaction_throttle: Arc<RangeCompactionThrottle>,
+
+    /// The largest safe point a GC task has been processed for, shared with
+    /// `GcWorker` so it can be inspected from outside.
+    applied_safe_point: Arc<AtomicU64>,
+
     cfg: GcConfig,
     cfg_tracker: Tracker<GcConfig>,
 
@@ -198,16 +261,22 @@ where
         raft_store_router: RR,
         cfg_tracker: Tracker<GcConfig>,
         cfg: GcConfig,
+        applied_safe_point: Arc<AtomicU64>,
     ) -> Self {
         let limiter = Limiter::new(if cfg.max_write_bytes_per_sec.0 > 0 {
             cfg.max_write_bytes_per_sec.0 as f64
         } else {
             INFINITY
         });
+        let range_compaction_throttle = Arc::new(RangeCompactionThrottle::new(
+            cfg.max_concurrent_range_compactions,
+        ));
         Self {
             engine,
             raft_store_router,
             limiter,
+            range_compaction_throttle,
+            applied_safe_point,
             cfg,
             cfg_tracker,
             stats: Statistics::default(),
@@ -513,6 +582,8 @@ where
             let limit = incoming.max_write_bytes_per_sec.0;
             self.limiter
                 .set_speed_limit(if limit > 0 { limit as f64 } else { INFINITY });
+            self.range_compaction_throttle
+                .set_limit(incoming.max_concurrent_range_compactions);
             self.cfg = incoming.clone();
         }
     }
@@ -553,6 +624,13 @@ where
                 ..
             } => {
                 let res = self.gc(&start_key, &end_key, safe_point);
+                // Only record the safe point once the task really finished,
+                // so waiters don't observe it while data is still being
+                // reclaimed.
+                if res.is_ok() {
+                    self.applied_safe_point
+                        .fetch_max(safe_point.into_inner(), Ordering::SeqCst);
+                }
                 update_metrics(res.is_err());
                 callback(res);
                 self.update_statistics_metrics();
@@ -572,6 +650,10 @@ where
             } => {
                 let old_seek_tombstone = self.stats.write.seek_tombstone;
                 let res = self.gc_keys(keys, safe_point, Some((store_id, region_info_provider)));
+                if res.is_ok() {
+                    self.applied_safe_point
+                        .fetch_max(safe_point.into_inner(), Ordering::SeqCst);
+                }
                 let new_seek_tombstone = self.stats.write.seek_tombstone;
                 let seek_tombstone = new_seek_tombstone - old_seek_tombstone;
                 slow_log!(T timer, "GC keys, seek_tombstone {}", seek_tombstone);
@@ -588,6 +670,7 @@ where
                 end_key,
                 callback,
             } => {
+                let _permit = self.range_compaction_throttle.acquire();
                 let res = self.unsafe_destroy_range(&ctx, &start_key, &end_key);
                 update_metrics(res.is_err());
                 callback(res);
@@ -708,6 +791,11 @@ where
 
     gc_manager_handle: Arc<Mutex<Option<GcManagerHandle>>>,
     feature_gate: FeatureGate,
+
+    /// The largest safe point the GC runner has completed a task for, or 0
+    /// if it hasn't processed any. Tests use it to confirm a safe point has
+    /// reached the store before asserting on reclaimed data.
+    applied_safe_point: Arc<AtomicU64>,
 }
 
 impl<E, RR> Clone for GcWorker<E, RR>
@@ -730,6 +818,7 @@ where
             applied_lock_collector: self.applied_lock_collector.clone(),
             gc_manager_handle: self.gc_manager_handle.clone(),
             feature_gate: self.feature_gate.clone(),
+            applied_safe_point: self.applied_safe_point.clone(),
         }
     }
 }
@@ -778,6 +867,7 @@ where
             applied_lock_collector: None,
             gc_manager_handle: Arc::new(Mutex::new(None)),
             feature_gate,
+            applied_safe_point: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -821,6 +911,7 @@ where
             self.raft_store_router.clone(),
             self.config_manager.0.clone().tracker("gc-woker".to_owned()),
             self.config_manager.value().clone(),
+            self.applied_safe_point.clone(),
         );
         self.worker
             .lock()
@@ -861,6 +952,12 @@ where
         self.worker_scheduler.clone()
     }
 
+    /// Returns the largest safe point the GC runner has completed a task
+    /// for, or 0 if it hasn't processed any.
+    pub fn applied_safe_point(&self) -> u64 {
+        self.applied_safe_point.load(Ordering::SeqCst)
+    }
+
     /// Check whether GCWorker is busy. If busy, callback will be invoked with an error that
     /// indicates GCWorker is busy; otherwise, return a new callback that invokes the original
     /// callback as well as decrease the scheduled task counter.
@@ -1456,4 +1553,36 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_range_compaction_throttle() {
+        let throttle = Arc::new(RangeCompactionThrottle::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (throttle, running, max_seen) =
+                    (throttle.clone(), running.clone(), max_seen.clone());
+                thread::spawn(move || {
+                    let _permit = throttle.acquire();
+                    let cur = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_seen.fetch_max(cur, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+        let max_seen = max_seen.load(Ordering::SeqCst);
+        assert!(max_seen > 0 && max_seen <= 2, "max concurrency {}", max_seen);
+
+        // Limit 0 means unlimited: permits never block.
+        let unlimited = RangeCompactionThrottle::new(0);
+        let _p1 = unlimited.acquire();
+        let _p2 = unlimited.acquire();
+        let _p3 = unlimited.acquire();
+    }
 }
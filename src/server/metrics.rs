@@ -291,6 +291,16 @@ lazy_static! {
         "Counter of occurrence of gc_worker being too busy"
     )
     .unwrap();
+    pub static ref GC_RANGE_COMPACTIONS_INFLIGHT: IntGauge = register_int_gauge!(
+        "tikv_gc_range_compactions_inflight",
+        "Number of region-range GC compactions currently running"
+    )
+    .unwrap();
+    pub static ref GC_RANGE_COMPACTIONS_QUEUED: IntGauge = register_int_gauge!(
+        "tikv_gc_range_compactions_queued",
+        "Number of region-range GC compactions waiting for a concurrency permit"
+    )
+    .unwrap();
     pub static ref AUTO_GC_STATUS_GAUGE_VEC: IntGaugeVec = register_int_gauge_vec!(
         "tikv_gcworker_autogc_status",
         "State of the auto gc manager",
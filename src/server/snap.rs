@@ -27,6 +27,7 @@ use raftstore::router::RaftStoreRouter;
 use raftstore::store::{SnapEntry, SnapKey, SnapManager, Snapshot};
 use security::SecurityManager;
 use tikv_util::config::{Tracker, VersionTrack};
+use tikv_util::sys::disk;
 use tikv_util::time::Instant;
 use tikv_util::worker::Runnable;
 use tikv_util::DeferContext;
@@ -305,6 +306,21 @@ fn recv_snap<R: RaftStoreRouter<impl KvEngine> + 'static>(
     }
 }
 
+/// Whether an incoming snapshot should be rejected because of disk
+/// pressure. Snapshots are always rejected once the disk is considered
+/// full; with `snap_recv_min_available_size` set they are rejected earlier,
+/// as soon as the published available space drops below the threshold. The
+/// sender gets a retriable error and the peer requests the snapshot again
+/// later.
+fn should_defer_recv(cfg: &Config) -> bool {
+    if disk::is_disk_full() {
+        return true;
+    }
+    let min_available = cfg.snap_recv_min_available_size.0;
+    // Available space is unknown until the stats task publishes it.
+    min_available > 0 && disk::get_disk_capacity() > 0 && disk::get_disk_available() < min_available
+}
+
 pub struct Runner<E, R>
 where
     E: KvEngine,
@@ -388,6 +404,15 @@ where
     fn run(&mut self, task: Task) {
         match task {
             Task::Recv { stream, sink } => {
+                if should_defer_recv(&self.cfg) {
+                    warn!("deferring snapshot reception, disk is almost full");
+                    let status = RpcStatus::with_message(
+                        RpcStatusCode::RESOURCE_EXHAUSTED,
+                        "the store is running out of disk space".to_owned(),
+                    );
+                    self.pool.spawn(sink.fail(status));
+                    return;
+                }
                 let task_num = self.recving_count.load(Ordering::SeqCst);
                 if task_num >= self.cfg.concurrent_recv_snap_limit {
                     warn!("too many recving snapshot tasks, ignore");
@@ -471,3 +496,36 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tikv_util::config::ReadableSize;
+
+    #[test]
+    fn test_should_defer_recv() {
+        // The disk state is process-global and shared with other tests.
+        let _lock = disk::test_exclusive_lock();
+        disk::clear_disk_full();
+        disk::set_disk_stats(0, 0);
+
+        let mut cfg = Config::default();
+        assert!(!should_defer_recv(&cfg));
+
+        // A full disk always rejects, regardless of the threshold.
+        disk::set_disk_full();
+        assert!(should_defer_recv(&cfg));
+        disk::clear_disk_full();
+        assert!(!should_defer_recv(&cfg));
+
+        cfg.snap_recv_min_available_size = ReadableSize::gb(1);
+        // Stats not published yet, so nothing is rejected.
+        disk::set_disk_stats(0, 0);
+        assert!(!should_defer_recv(&cfg));
+        disk::set_disk_stats(ReadableSize::gb(10).0, ReadableSize::mb(512).0);
+        assert!(should_defer_recv(&cfg));
+        disk::set_disk_stats(ReadableSize::gb(10).0, ReadableSize::gb(2).0);
+        assert!(!should_defer_recv(&cfg));
+        disk::set_disk_stats(0, 0);
+    }
+}
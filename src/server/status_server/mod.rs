@@ -45,6 +45,7 @@ use security::{self, SecurityConfig};
 use tikv_alloc::error::ProfError;
 use tikv_util::logger::set_log_level;
 use tikv_util::metrics::dump;
+use tikv_util::sys::disk;
 use tikv_util::timer::GLOBAL_TIMER_HANDLE;
 
 use super::Result;
@@ -288,6 +289,22 @@ where
         })
     }
 
+    /// Reports the current disk state of the store. The snapshot is refreshed
+    /// by the periodic storage stats task, so serving it never touches the
+    /// filesystem.
+    fn get_disk_usage() -> hyper::Result<Response<Body>> {
+        let body = serde_json::json!({
+            "usage": if disk::is_disk_full() { "full" } else { "normal" },
+            "capacity": disk::get_disk_capacity(),
+            "available": disk::get_disk_available(),
+            "reserved_space": disk::get_disk_reserved_space(),
+        });
+        Ok(Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap())
+    }
+
     async fn update_config(
         cfg_controller: ConfigController,
         req: Request<Body>,
@@ -726,6 +743,7 @@ where
                                 | (&Method::GET, "/status")
                                 | (&Method::GET, "/config")
                                 | (&Method::GET, "/debug/pprof/profile")
+                                | (&Method::GET, "/disk_usage")
                         );
 
                         if should_check_cert && !check_cert(security_config, x509) {
@@ -738,6 +756,7 @@ where
                         match (method, path.as_ref()) {
                             (Method::GET, "/metrics") => Ok(Response::new(dump().into())),
                             (Method::GET, "/status") => Ok(Response::default()),
+                            (Method::GET, "/disk_usage") => Self::get_disk_usage(),
                             (Method::GET, "/debug/pprof/heap") => {
                                 Self::dump_prof_to_resp(req).await
                             }
@@ -1044,8 +1063,10 @@ mod tests {
     use raftstore::store::transport::CasualRouter;
     use raftstore::store::CasualMessage;
     use security::SecurityConfig;
+    use serde_json::Value;
     use test_util::new_security_cfg;
     use tikv_util::logger::get_log_level;
+    use tikv_util::sys::disk;
 
     #[derive(Clone)]
     struct MockRouter;
@@ -1084,6 +1105,44 @@ mod tests {
         status_server.stop();
     }
 
+    #[test]
+    fn test_disk_usage_endpoint() {
+        let mut status_server = StatusServer::new(
+            1,
+            None,
+            ConfigController::default(),
+            Arc::new(SecurityConfig::default()),
+            MockRouter,
+        )
+        .unwrap();
+        let addr = "127.0.0.1:0".to_owned();
+        let _ = status_server.start(addr.clone(), addr);
+        // The disk state is process-global and shared with other tests.
+        let _lock = disk::test_exclusive_lock();
+        disk::clear_disk_full();
+        disk::set_disk_stats(1024, 512);
+        let client = Client::new();
+        let uri = Uri::builder()
+            .scheme("http")
+            .authority(status_server.listening_addr().to_string().as_str())
+            .path_and_query("/disk_usage")
+            .build()
+            .unwrap();
+
+        let handle = status_server.thread_pool.spawn(async move {
+            let res = client.get(uri).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            let json: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["capacity"], 1024);
+            assert_eq!(json["available"], 512);
+            assert_eq!(json["usage"], "normal");
+        });
+        block_on(handle).unwrap();
+        disk::set_disk_stats(0, 0);
+        status_server.stop();
+    }
+
     #[test]
     fn test_security_status_service_without_cn() {
         do_test_security_status_service(HashSet::default(), true);
@@ -8,13 +8,16 @@ use crate::server::CONFIG_ROCKSDB_GAUGE;
 use engine_rocks::raw::{Cache, LRUCacheOptions, MemoryAllocator};
 use engine_rocks::RocksEngine;
 use engine_traits::{CFOptionsExt, ColumnFamilyOptions, CF_DEFAULT};
-use file_system::{get_io_rate_limiter, IOPriority, IORateLimitMode, IORateLimiter, IOType};
+use file_system::{
+    get_io_rate_limiter, IOBudgetCurve, IOPriority, IORateLimitMode, IORateLimiter, IOType,
+};
 use libc::c_int;
 use online_config::{ConfigChange, ConfigManager, ConfigValue, OnlineConfig, Result as CfgResult};
+use std::cmp;
 use std::error::Error;
 use strum::IntoEnumIterator;
 use tikv_util::config::{self, OptionReadableSize, ReadableDuration, ReadableSize};
-use tikv_util::sys::SysQuota;
+use tikv_util::sys::{disk, SysQuota};
 use tikv_util::worker::Scheduler;
 
 pub const DEFAULT_DATA_DIR: &str = "./";
@@ -48,9 +51,19 @@ pub struct Config {
     pub scheduler_worker_pool_size: usize,
     #[online_config(skip)]
     pub scheduler_pending_write_threshold: ReadableSize,
-    #[online_config(skip)]
     // Reserve disk space to make tikv would have enough space to compact when disk is full.
     pub reserve_space: ReadableSize,
+    /// Skips creating the space placeholder file while still accounting the
+    /// reserved space, so short-lived test servers don't pay for the
+    /// allocation. Recovering from a full disk then has no space to free, so
+    /// don't enable it in production.
+    #[online_config(skip)]
+    pub skip_placeholder_file: bool,
+    /// Treat a mount as full when its available inodes drop to this number.
+    /// Filesystems with many small SSTs can exhaust inodes before bytes.
+    /// 0 (the default) disables the inode check.
+    #[online_config(skip)]
+    pub reserve_inodes: u64,
     #[online_config(skip)]
     pub enable_async_apply_prewrite: bool,
     #[online_config(skip)]
@@ -74,6 +87,8 @@ impl Default for Config {
             scheduler_worker_pool_size: if cpu_num >= 16.0 { 8 } else { 4 },
             scheduler_pending_write_threshold: ReadableSize::mb(DEFAULT_SCHED_PENDING_WRITE_MB),
             reserve_space: ReadableSize::gb(DEFAULT_RESERVED_SPACE_GB),
+            skip_placeholder_file: false,
+            reserve_inodes: 0,
             enable_async_apply_prewrite: false,
             enable_ttl: false,
             ttl_check_poll_interval: ReadableDuration::hours(12),
@@ -100,10 +115,25 @@ impl Config {
     }
 }
 
+/// Calculate the size of the space placeholder file from the disk capacity
+/// and the configured `storage.reserve-space`. Zero disables reservation,
+/// otherwise the larger one of the configured size and 5% of the capacity
+/// is reserved.
+pub fn calculate_reserved_space(capacity: u64, reserve_from_config: u64) -> u64 {
+    if reserve_from_config == 0 {
+        0
+    } else {
+        cmp::max((capacity as f64 * 0.05) as u64, reserve_from_config)
+    }
+}
+
 pub struct StorageConfigManger {
     kvdb: RocksEngine,
     shared_block_cache: bool,
     ttl_checker_scheduler: Scheduler<TTLCheckerTask>,
+    data_dir: String,
+    config_capacity: ReadableSize,
+    skip_placeholder_file: bool,
 }
 
 impl StorageConfigManger {
@@ -111,11 +141,17 @@ impl StorageConfigManger {
         kvdb: RocksEngine,
         shared_block_cache: bool,
         ttl_checker_scheduler: Scheduler<TTLCheckerTask>,
+        data_dir: String,
+        config_capacity: ReadableSize,
+        skip_placeholder_file: bool,
     ) -> StorageConfigManger {
         StorageConfigManger {
             kvdb,
             shared_block_cache,
             ttl_checker_scheduler,
+            data_dir,
+            config_capacity,
+            skip_placeholder_file,
         }
     }
 }
@@ -147,6 +183,24 @@ impl ConfigManager for StorageConfigManger {
                 .schedule(TTLCheckerTask::UpdatePollInterval(interval.into()))
                 .unwrap();
         }
+        if let Some(v) = change.remove("reserve_space") {
+            let reserve_space: ReadableSize = v.into();
+            let disk_stats = fs2::statvfs(&self.data_dir).map_err(|e| format!("{}", e))?;
+            let mut capacity = disk_stats.total_space();
+            if self.config_capacity.0 > 0 {
+                capacity = cmp::min(capacity, self.config_capacity.0);
+            }
+            let reserved = calculate_reserved_space(capacity, reserve_space.0);
+            if !self.skip_placeholder_file {
+                file_system::reserve_space_for_recover(&self.data_dir, reserved)
+                    .map_err(|e| format!("{}", e))?;
+            }
+            disk::set_disk_reserved_space(reserved);
+            info!("update disk reserved space";
+                "reserve_space" => reserve_space.0,
+                "placeholder_size" => reserved,
+            );
+        }
         if let Some(ConfigValue::Module(mut io_rate_limit)) = change.remove("io_rate_limit") {
             let limiter = match get_io_rate_limiter() {
                 None => return Err("IO rate limiter is not present".into()),
@@ -263,6 +317,18 @@ pub struct IORateLimitConfig {
     /// turned on.
     #[online_config(skip)]
     pub strict: bool,
+    /// The curve used to grow the rate limited IO budgets with compaction
+    /// pending bytes. `sqrt` (the default) converges with low backlog,
+    /// `linear` and `quadratic` trade some backlog for steadier foreground
+    /// flow.
+    #[online_config(skip)]
+    pub budget_curve: IOBudgetCurve,
+    /// The fraction of the IO bandwidth always granted to rate limited IO,
+    /// i.e. the lower bound the budget curve slides from. Raise it on
+    /// devices with high compaction write amplification so sustained write
+    /// pressure can't starve compaction. Must be within (0, 1).
+    #[online_config(skip)]
+    pub budget_floor: f64,
     pub foreground_read_priority: IOPriority,
     pub foreground_write_priority: IOPriority,
     pub flush_priority: IOPriority,
@@ -282,6 +348,8 @@ impl Default for IORateLimitConfig {
             max_bytes_per_sec: ReadableSize::mb(0),
             mode: IORateLimitMode::WriteOnly,
             strict: false,
+            budget_curve: IOBudgetCurve::Sqrt,
+            budget_floor: 0.5,
             foreground_read_priority: IOPriority::High,
             foreground_write_priority: IOPriority::High,
             flush_priority: IOPriority::High,
@@ -338,6 +406,13 @@ impl IORateLimitConfig {
             );
             self.gc_priority = self.foreground_write_priority;
         }
+        if self.budget_floor <= 0.0 || self.budget_floor >= 1.0 {
+            return Err(format!(
+                "storage.io-rate-limit.budget-floor must be within (0, 1), got {}",
+                self.budget_floor
+            )
+            .into());
+        }
         if self.mode != IORateLimitMode::WriteOnly {
             return Err(
                 "storage.io-rate-limit.mode other than write-only is not supported.".into(),
@@ -6,6 +6,7 @@ extern crate test;
 
 mod coprocessor;
 mod keybuilder;
+mod raft_engine;
 mod raftkv;
 mod serialization;
 mod storage;
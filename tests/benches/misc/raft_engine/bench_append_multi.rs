@@ -0,0 +1,53 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+use engine_rocks::util::new_default_engine;
+use engine_rocks::RocksEngine;
+use engine_traits::{RaftEngine, RaftLogBatch};
+use raft::eraftpb::Entry;
+use tempfile::Builder;
+use test::Bencher;
+
+const REGIONS: u64 = 50;
+const ENTRIES_PER_REGION: u64 = 20;
+
+fn new_raft_engine(prefix: &str) -> (RocksEngine, tempfile::TempDir) {
+    let dir = Builder::new().prefix(prefix).tempdir().unwrap();
+    let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+    (engine, dir)
+}
+
+fn region_entries(region_id: u64) -> Vec<Entry> {
+    (0..ENTRIES_PER_REGION)
+        .map(|i| {
+            let mut e = Entry::default();
+            e.set_index(i + 1);
+            e.set_data(vec![region_id as u8; 64].into());
+            e
+        })
+        .collect()
+}
+
+#[bench]
+fn bench_append_per_region(b: &mut Bencher) {
+    let (engine, _dir) = new_raft_engine("bench_append_per_region");
+    b.iter(|| {
+        let mut batch = engine.log_batch(0);
+        for region_id in 1..=REGIONS {
+            batch.append(region_id, region_entries(region_id)).unwrap();
+        }
+        test::black_box(batch);
+    });
+}
+
+#[bench]
+fn bench_append_multi(b: &mut Bencher) {
+    let (engine, _dir) = new_raft_engine("bench_append_multi");
+    b.iter(|| {
+        let mut batch = engine.log_batch(0);
+        let groups = (1..=REGIONS)
+            .map(|region_id| (region_id, None, region_entries(region_id)))
+            .collect();
+        batch.append_multi(groups).unwrap();
+        test::black_box(batch);
+    });
+}
@@ -0,0 +1,58 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+use engine_rocks::util::new_default_engine;
+use engine_rocks::{RocksAsyncLogWriter, RocksEngine};
+use engine_traits::{RaftEngine, RaftLogBatch};
+use raft::eraftpb::Entry;
+use tempfile::Builder;
+use test::Bencher;
+
+const ROUNDS: u64 = 64;
+const ENTRIES_PER_BATCH: u64 = 8;
+
+fn new_raft_engine(prefix: &str) -> (RocksEngine, tempfile::TempDir) {
+    let dir = Builder::new().prefix(prefix).tempdir().unwrap();
+    let engine = new_default_engine(dir.path().to_str().unwrap()).unwrap();
+    (engine, dir)
+}
+
+fn fill_batch(engine: &RocksEngine, round: u64) -> <RocksEngine as RaftEngine>::LogBatch {
+    let mut batch = engine.log_batch(0);
+    let entries = (0..ENTRIES_PER_BATCH)
+        .map(|i| {
+            let mut e = Entry::default();
+            e.set_index(round * ENTRIES_PER_BATCH + i + 1);
+            e.set_data(vec![0; 32].into());
+            e
+        })
+        .collect();
+    batch.append(1, entries).unwrap();
+    batch
+}
+
+#[bench]
+fn bench_consume_sync(b: &mut Bencher) {
+    let (engine, _dir) = new_raft_engine("bench_consume_sync");
+    b.iter(|| {
+        for r in 0..ROUNDS {
+            let mut batch = fill_batch(&engine, r);
+            engine.consume(&mut batch, true).unwrap();
+        }
+    });
+}
+
+#[bench]
+fn bench_consume_async(b: &mut Bencher) {
+    let (engine, _dir) = new_raft_engine("bench_consume_async");
+    let writer = RocksAsyncLogWriter::new(engine.clone());
+    b.iter(|| {
+        // Pipelined: submit all batches, then wait for the results so batch
+        // building overlaps with writing.
+        let receivers: Vec<_> = (0..ROUNDS)
+            .map(|r| writer.consume_async(fill_batch(&engine, r), true))
+            .collect();
+        for rx in receivers {
+            rx.recv().unwrap().unwrap();
+        }
+    });
+}
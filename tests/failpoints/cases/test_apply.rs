@@ -0,0 +1,34 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+use std::time::Duration;
+
+use test_raftstore::*;
+
+// Test that an injected apply error is returned to the proposer and doesn't
+// leave the rejected write behind, and that clearing it resumes normal apply.
+#[test]
+fn test_inject_apply_error() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.run();
+    cluster.must_transfer_leader(1, new_peer(1, 1));
+    cluster.must_put(b"k1", b"v1");
+
+    // Fail everything of region 1 applied on the leader from now on.
+    cluster.inject_apply_error(1, 1, 0);
+    let req = new_request(
+        1,
+        cluster.get_region_epoch(1),
+        vec![new_put_cmd(b"k2", b"v2")],
+        false,
+    );
+    let resp = cluster
+        .call_command_on_leader(req, Duration::from_secs(3))
+        .unwrap();
+    assert!(resp.get_header().has_error(), "{:?}", resp);
+    // The rejected write must be rolled back on the failing store.
+    must_get_none(&cluster.get_engine(1), b"k2");
+
+    cluster.clear_apply_error(1);
+    cluster.must_put(b"k3", b"v3");
+    must_get_equal(&cluster.get_engine(1), b"k3", b"v3");
+}
@@ -67,3 +67,40 @@ fn test_evict_entry_cache() {
     fail::cfg("needs_evict_entry_cache", "off").unwrap();
     fail::cfg("on_entry_cache_evict_tick", "off").unwrap();
 }
+
+#[test]
+fn test_raft_engine_gc_flush_batch_keys() {
+    use engine_traits::RaftEngine;
+    use raft::eraftpb::Entry;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let dir = tempfile::Builder::new()
+        .prefix("test_raft_engine_gc_flush_batch_keys")
+        .tempdir()
+        .unwrap();
+    let mut engine = engine_rocks::util::new_default_engine(dir.path().to_str().unwrap()).unwrap();
+    engine.set_gc_flush_batch_keys(4);
+
+    let entries: Vec<_> = (1..=10)
+        .map(|i| {
+            let mut e = Entry::default();
+            e.set_index(i);
+            e
+        })
+        .collect();
+    engine.append(1, entries).unwrap();
+
+    let flushes = Arc::new(AtomicUsize::new(0));
+    let flushes_ = flushes.clone();
+    fail::cfg_callback("raft_engine_gc_flush", move || {
+        flushes_.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    // 10 deletes with a threshold of 4 flush twice in the loop plus once
+    // for the 2 leftovers.
+    engine.gc(1, 1, 11).unwrap();
+    assert_eq!(flushes.load(Ordering::SeqCst), 3);
+    fail::remove("raft_engine_gc_flush");
+}
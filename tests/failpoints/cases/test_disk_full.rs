@@ -84,6 +84,33 @@ fn test_disk_full_allowed_leader_behaviors() {
     fail::remove("disk_full_peer_1");
 }
 
+#[test]
+fn test_disk_full_allowed_cfs() {
+    let mut cluster = new_server_cluster(0, 3);
+    cluster.cfg.raft_store.disk_full_allowed_cfs = vec!["lock".to_owned()];
+    cluster.pd_client.disable_default_operator();
+    cluster.run();
+
+    // To ensure all replicas are not pending.
+    cluster.must_put(b"k1", b"v1");
+    must_get_equal(&cluster.get_engine(1), b"k1", b"v1");
+    must_get_equal(&cluster.get_engine(2), b"k1", b"v1");
+    must_get_equal(&cluster.get_engine(3), b"k1", b"v1");
+
+    cluster.must_transfer_leader(1, new_peer(1, 1));
+    fail::cfg("disk_full_peer_1", "return").unwrap();
+
+    // Writes to the default cf are still rejected.
+    let rx = cluster.async_put(b"k2", b"v2").unwrap();
+    assert_disk_full(&rx.recv_timeout(Duration::from_secs(2)).unwrap());
+
+    // Writes touching only allowed cfs can still proceed.
+    cluster.must_put_cf("lock", b"k2", b"v2");
+    must_get_cf_equal(&cluster.get_engine(1), "lock", b"k2", b"v2");
+
+    fail::remove("disk_full_peer_1");
+}
+
 #[test]
 fn test_disk_full_follower_behaviors() {
     let mut cluster = new_server_cluster(0, 3);
@@ -639,3 +639,28 @@ fn test_batch_read_index_after_transfer_leader() {
         assert_eq!(entry.get_term(), term);
     }
 }
+
+#[test]
+fn test_read_delay_on_single_store() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    let region = cluster.get_region(b"k1");
+    cluster.must_transfer_leader(region.get_id(), new_peer(1, 1));
+
+    // Reads on the leader store are slowed down, writes are not.
+    cluster.set_read_delay(1, Duration::from_millis(300));
+    let now = std::time::Instant::now();
+    cluster.must_put(b"k2", b"v2");
+    assert!(now.elapsed() < Duration::from_millis(300));
+    let now = std::time::Instant::now();
+    assert_eq!(cluster.must_get(b"k1").unwrap(), b"v1");
+    assert!(now.elapsed() >= Duration::from_millis(300));
+
+    // Clearing the delay restores fast reads.
+    cluster.clear_read_delay(1);
+    let now = std::time::Instant::now();
+    assert_eq!(cluster.must_get(b"k1").unwrap(), b"v1");
+    assert!(now.elapsed() < Duration::from_millis(300));
+}
@@ -8,6 +8,7 @@ use kvproto::tikvpb::TikvClient;
 use pd_client::PdClient;
 use raft::eraftpb::MessageType;
 use std::sync::Arc;
+use std::time::Duration;
 use test_raftstore::*;
 use tikv_util::HandyRwLock;
 
@@ -709,3 +710,25 @@ fn test_stale_read_future_ts_not_update_max_ts() {
     // `key3` is write as 1pc transaction so we can read `key3` without commit
     leader_client.must_kv_read_equal(b"key3".to_vec(), b"value1".to_vec(), get_tso(&pd_client));
 }
+
+// Testing that waiting for the follower's safe-ts to pass the commit ts makes
+// the following stale read deterministic instead of racing the resolved-ts
+// advance.
+#[test]
+fn test_stale_read_waits_for_safe_ts() {
+    let (cluster, pd_client, leader_client) = prepare_for_stale_read(new_peer(1, 1));
+    let mut follower_client2 = PeerClient::new(&cluster, 1, new_peer(2, 2));
+    follower_client2.ctx.set_stale_read(true);
+
+    // An unknown region has no resolved safe-ts yet.
+    assert_eq!(cluster.region_safe_ts(999, 2), 0);
+
+    let commit_ts = leader_client.must_kv_write(
+        &pd_client,
+        vec![new_mutation(Op::Put, &b"key1"[..], &b"value1"[..])],
+        b"key1".to_vec(),
+    );
+    cluster.wait_safe_ts_gte(1, 2, commit_ts, Duration::from_secs(5));
+    assert!(cluster.region_safe_ts(1, 2) >= commit_ts);
+    follower_client2.must_kv_read_equal(b"key1".to_vec(), b"value1".to_vec(), commit_ts);
+}
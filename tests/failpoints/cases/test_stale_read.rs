@@ -525,3 +525,55 @@ fn test_stale_read_during_merging_2() {
 
     fail::remove(leader_commit_prepare_merge_fp);
 }
+
+#[test]
+fn test_expire_leader_lease() {
+    let mut cluster = new_node_cluster(0, 3);
+    configure_for_lease_read(&mut cluster, Some(50), None);
+    cluster.run();
+
+    cluster.must_put(b"k1", b"v1");
+    let region = cluster.get_region(b"k1");
+    let peer1 = find_peer(&region, 1).unwrap().to_owned();
+    cluster.must_transfer_leader(region.get_id(), peer1.clone());
+
+    // Warm up the lease so the read below would be served locally without
+    // the clock jump.
+    must_read_on_peer(&mut cluster, peer1, region.clone(), b"k1", b"v1");
+
+    // After expiring the lease, the next read on the leader has to go
+    // through read index again.
+    cluster.expire_leader_lease(region.get_id());
+    cluster.must_read_index_fallback(region.get_id(), b"k1", b"v1");
+
+    // The read index also renews the lease, so expiring works repeatedly.
+    cluster.expire_leader_lease(region.get_id());
+    cluster.must_read_index_fallback(region.get_id(), b"k1", b"v1");
+}
+
+#[test]
+fn test_read_path_counts() {
+    let mut cluster = new_node_cluster(0, 3);
+    configure_for_lease_read(&mut cluster, Some(50), None);
+    cluster.run();
+
+    cluster.must_put(b"k1", b"v1");
+    let region = cluster.get_region(b"k1");
+    let peer1 = find_peer(&region, 1).unwrap().to_owned();
+    cluster.must_transfer_leader(region.get_id(), peer1.clone());
+
+    // Warm up the lease, then a read must take the local lease path.
+    must_read_on_peer(&mut cluster, peer1.clone(), region.clone(), b"k1", b"v1");
+    cluster.reset_read_path_counts(1);
+    must_read_on_peer(&mut cluster, peer1.clone(), region.clone(), b"k1", b"v1");
+    let (lease_reads, read_index_reads) = cluster.read_path_counts(1);
+    assert!(lease_reads > 0, "{} {}", lease_reads, read_index_reads);
+    assert_eq!(read_index_reads, 0);
+
+    // After expiring the lease the next read must go through read index.
+    cluster.expire_leader_lease(region.get_id());
+    cluster.reset_read_path_counts(1);
+    must_read_on_peer(&mut cluster, peer1, region, b"k1", b"v1");
+    let (_, read_index_reads) = cluster.read_path_counts(1);
+    assert!(read_index_reads > 0);
+}
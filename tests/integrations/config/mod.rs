@@ -14,7 +14,7 @@ use engine_rocks::raw::{
     CompactionPriority, DBCompactionStyle, DBCompressionType, DBRateLimiterMode, DBRecoveryMode,
 };
 use engine_traits::config::PerfLevel;
-use file_system::{IOPriority, IORateLimitMode};
+use file_system::{IOBudgetCurve, IOPriority, IORateLimitMode};
 use kvproto::encryptionpb::EncryptionMethod;
 use pd_client::Config as PdConfig;
 use raftstore::coprocessor::{Config as CopConfig, ConsistencyCheckMethod};
@@ -97,6 +97,9 @@ fn test_serde_custom_tikv_config() {
         end_point_max_concurrency: 10,
         snap_max_write_bytes_per_sec: ReadableSize::mb(10),
         snap_max_total_size: ReadableSize::gb(10),
+        snap_recv_min_available_size: ReadableSize::gb(1),
+        clock_drift_threshold: ReadableDuration::secs(2),
+        panic_on_clock_drift: true,
         stats_concurrency: 10,
         heavy_load_threshold: 1000,
         heavy_load_wait_duration: ReadableDuration::millis(2),
@@ -534,6 +537,8 @@ fn test_serde_custom_tikv_config() {
         allow_concurrent_memtable_write: false,
         bytes_per_sync: ReadableSize::mb(1),
         wal_bytes_per_sync: ReadableSize::kb(32),
+        enable_log_checksum: true,
+        gc_batch_keys: 64,
         defaultcf: RaftDefaultCfConfig {
             block_size: ReadableSize::kb(12),
             block_cache_size: ReadableSize::gb(12),
@@ -596,6 +601,8 @@ fn test_serde_custom_tikv_config() {
         scheduler_worker_pool_size: 1,
         scheduler_pending_write_threshold: ReadableSize::kb(123),
         reserve_space: ReadableSize::gb(10),
+        skip_placeholder_file: true,
+        reserve_inodes: 2048,
         enable_async_apply_prewrite: true,
         enable_ttl: true,
         ttl_check_poll_interval: ReadableDuration::hours(0),
@@ -611,6 +618,8 @@ fn test_serde_custom_tikv_config() {
             max_bytes_per_sec: ReadableSize::mb(1000),
             mode: IORateLimitMode::AllIo,
             strict: true,
+            budget_curve: IOBudgetCurve::Linear,
+            budget_floor: 0.6,
             foreground_read_priority: IOPriority::Low,
             foreground_write_priority: IOPriority::Low,
             flush_priority: IOPriority::Low,
@@ -674,6 +683,7 @@ fn test_serde_custom_tikv_config() {
         max_write_bytes_per_sec: ReadableSize::mb(10),
         enable_compaction_filter: false,
         compaction_filter_skip_version_check: true,
+        max_concurrent_range_compactions: 4,
     };
     value.pessimistic_txn = PessimisticTxnConfig {
         wait_for_lock_timeout: ReadableDuration::millis(10),
@@ -947,3 +947,28 @@ fn test_conf_change_fast() {
     must_get_equal(&cluster.get_engine(2), b"k1", b"v1");
     assert!(timer.saturating_elapsed() < Duration::from_secs(5));
 }
+
+#[test]
+fn test_kill_store_permanently_and_replace() {
+    let mut cluster = new_node_cluster(0, 3);
+    let pd_client = Arc::clone(&cluster.pd_client);
+    pd_client.disable_default_operator();
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    cluster.kill_store_permanently(3);
+    // The permanent failure is observable in PD and survives a restart.
+    assert_eq!(
+        pd_client.get_store(3).unwrap().get_state(),
+        metapb::StoreState::Tombstone
+    );
+
+    // Replace it with a fresh store and move the replica over.
+    let new_store = cluster.replace_dead_store().unwrap();
+    assert_ne!(new_store, 3);
+    pd_client.must_add_peer(1, new_peer(new_store, new_store));
+    pd_client.must_remove_peer(1, new_peer(3, 3));
+
+    cluster.must_put(b"k2", b"v2");
+    must_get_equal(&cluster.get_engine(new_store), b"k2", b"v2");
+}
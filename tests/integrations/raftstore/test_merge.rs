@@ -1193,3 +1193,25 @@ fn test_sync_max_ts_after_region_merge() {
     let new_max_ts = cm.max_ts();
     assert!(new_max_ts > max_ts);
 }
+
+/// Test the helper that merges two regions and verifies the source's last
+/// committed write is applied on the target before the merge commit.
+#[test]
+fn test_node_merge_verify_order() {
+    let mut cluster = new_node_cluster(0, 3);
+    configure_for_merge(&mut cluster);
+    cluster.run();
+
+    cluster.must_put(b"k1", b"v1");
+    cluster.must_put(b"k3", b"v3");
+
+    let pd_client = Arc::clone(&cluster.pd_client);
+    let region = pd_client.get_region(b"k1").unwrap();
+    cluster.must_split(&region, b"k2");
+    let left = pd_client.get_region(b"k1").unwrap();
+    let right = pd_client.get_region(b"k3").unwrap();
+
+    // The last write to the source right before merging must survive it.
+    cluster.must_put(b"k11", b"v11");
+    cluster.must_merge_and_verify_order(left.get_id(), right.get_id(), b"k11");
+}
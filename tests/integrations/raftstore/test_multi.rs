@@ -820,3 +820,29 @@ fn test_node_catch_up_logs() {
     cluster.run_node(3).unwrap();
     must_get_equal(&cluster.get_engine(3), b"0009", b"0009");
 }
+
+#[test]
+fn test_region_data_checksum() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.run();
+
+    for i in 0..10 {
+        let key = format!("k{:03}", i).into_bytes();
+        cluster.must_put(&key, b"value");
+    }
+    // Wait until every replica has applied the last write.
+    for store_id in 1..=3 {
+        must_get_equal(&cluster.get_engine(store_id), b"k009", b"value");
+    }
+
+    // All replicas hold the same user data.
+    let region_id = cluster.get_region_id(b"k000");
+    let checksum = cluster.region_data_checksum(region_id, 1);
+    assert_eq!(checksum, cluster.region_data_checksum(region_id, 2));
+    assert_eq!(checksum, cluster.region_data_checksum(region_id, 3));
+
+    // New data changes the checksum.
+    cluster.must_put(b"k010", b"value");
+    must_get_equal(&cluster.get_engine(1), b"k010", b"value");
+    assert_ne!(checksum, cluster.region_data_checksum(region_id, 1));
+}
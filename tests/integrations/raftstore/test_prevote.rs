@@ -306,3 +306,26 @@ fn test_node_create_peer_from_pre_vote() {
     cluster.cfg.raft_store.prevote = true;
     test_create_peer_from_pre_vote(&mut cluster);
 }
+
+// With pre-vote enabled, partitioning and healing a follower must not bump
+// the term observed by the majority. `assert_term_stable_during` wraps that
+// invariant check.
+#[test]
+fn test_node_term_stable_during_partition() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.cfg.raft_store.prevote = true;
+    cluster.cfg.raft_store.hibernate_regions = false;
+    cluster.run();
+
+    cluster.must_transfer_leader(1, new_peer(1, 1));
+    cluster.must_put(b"k1", b"v1");
+
+    cluster.assert_term_stable_during(1, 1, |c| {
+        c.add_send_filter(IsolationFilterFactory::new(3));
+        // Give the isolated follower time to start campaigning.
+        thread::sleep(Duration::from_millis(500));
+        c.clear_send_filters();
+        c.must_put(b"k2", b"v2");
+        must_get_equal(&c.get_engine(3), b"k2", b"v2");
+    });
+}
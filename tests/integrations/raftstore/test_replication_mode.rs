@@ -362,3 +362,26 @@ fn test_loading_label_after_rolling_start() {
     assert_eq!(state.state_id, 1);
     assert_eq!(state.state, RegionReplicationState::IntegrityOverLabel);
 }
+
+#[test]
+fn test_update_store_labels() {
+    let mut cluster = new_server_cluster(0, 3);
+    cluster.add_label(1, "zone", "ES");
+    cluster.run();
+    cluster.must_store_has_labels(1, &[("zone".to_owned(), "ES".to_owned())]);
+
+    cluster.update_store_labels(
+        1,
+        &[
+            ("zone".to_owned(), "WS".to_owned()),
+            ("host".to_owned(), "h1".to_owned()),
+        ],
+    );
+    cluster.must_store_has_labels(
+        1,
+        &[
+            ("zone".to_owned(), "WS".to_owned()),
+            ("host".to_owned(), "h1".to_owned()),
+        ],
+    );
+}
@@ -202,3 +202,16 @@ fn test_node_apply_no_op() {
         sleep_ms(10);
     }
 }
+
+#[test]
+fn test_node_bench_writes() {
+    let mut cluster = new_node_cluster(0, 1);
+    cluster.run();
+
+    let stats = cluster.bench_writes(b"k1", 50, 32);
+    assert!(stats.p50 <= stats.p99, "{:?}", stats);
+    assert!(stats.p99 <= stats.max, "{:?}", stats);
+    assert!(stats.max > Duration::from_secs(0), "{:?}", stats);
+    // The benched key really got written.
+    assert_eq!(cluster.get(b"k1"), Some(vec![b'x'; 32]));
+}
@@ -889,3 +889,16 @@ fn test_split_with_epoch_not_match() {
         .unwrap();
     assert!(resp.get_header().get_error().has_epoch_not_match());
 }
+
+#[test]
+fn test_split_advances_epoch_version() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    let region = cluster.get_region(b"k1");
+    let region_id = region.get_id();
+    // A split bumps the version of the derived region and leaves conf_ver
+    // untouched.
+    cluster.assert_epoch_advanced(region_id, |c| c.must_split(&region, b"k2"), 1, 0);
+}
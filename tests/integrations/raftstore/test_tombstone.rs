@@ -5,6 +5,7 @@ use std::thread;
 use std::time::Duration;
 
 use crossbeam::channel;
+use futures::executor::block_on;
 use kvproto::raft_serverpb::{PeerState, RaftMessage, RegionLocalState, StoreIdent};
 use protobuf::Message;
 use raft::eraftpb::MessageType;
@@ -337,3 +338,29 @@ fn test_safe_tombstone_gc() {
     thread::sleep(base_tick_interval * tick as u32 * 3);
     must_get_equal(&cluster.get_engine(5), b"k1", b"v1");
 }
+
+#[test]
+fn test_wait_all_regions_peer_state() {
+    let mut cluster = new_node_cluster(0, 3);
+    let pd_client = Arc::clone(&cluster.pd_client);
+    pd_client.disable_default_operator();
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    let region = cluster.get_region(b"k1");
+    cluster.must_split(&region, b"k2");
+
+    // Decommission store 3: every region on it must become tombstone.
+    for &id in &cluster.regions_on_store(3) {
+        let peer = block_on(pd_client.get_region_by_id(id))
+            .unwrap()
+            .unwrap()
+            .get_peers()
+            .iter()
+            .find(|p| p.get_store_id() == 3)
+            .unwrap()
+            .clone();
+        pd_client.must_remove_peer(id, peer);
+    }
+    cluster.wait_all_regions_peer_state(3, PeerState::Tombstone, Duration::from_secs(5));
+}
@@ -62,6 +62,80 @@ fn test_server_basic_transfer_leader() {
     test_basic_transfer_leader(&mut cluster);
 }
 
+#[test]
+fn test_pending_pd_operators() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.pd_client.disable_default_operator();
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+    assert!(cluster.pending_pd_operators(1).is_empty());
+
+    let leader = cluster.leader_of_region(1).unwrap();
+    let target = cluster
+        .get_region(b"k1")
+        .get_peers()
+        .iter()
+        .find(|p| p.get_id() != leader.get_id())
+        .unwrap()
+        .clone();
+    cluster.pd_client.transfer_leader(1, target.clone());
+    let desc = cluster.wait_pd_operator(1, OperatorKind::TransferLeader, Duration::from_secs(5));
+    match desc {
+        OperatorDesc::TransferLeader(p) => assert_eq!(p, target),
+        d => panic!("unexpected operator {:?}", d),
+    }
+    cluster.must_transfer_leader(1, target);
+}
+
+#[test]
+fn test_call_command_on_leader_with_opts() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+    cluster.must_transfer_leader(1, new_peer(2, 2));
+
+    // Isolate the cached leader, then wait for it to step down and for the
+    // rest to elect a new one. The next command meets a few not-leader
+    // responses before the refreshed leader applies it.
+    cluster.add_send_filter(IsolationFilterFactory::new(2));
+    let election_timeout = Duration::from_millis(
+        cluster.cfg.raft_store.raft_base_tick_interval.as_millis()
+            * cluster.cfg.raft_store.raft_election_timeout_ticks as u64,
+    );
+    thread::sleep(election_timeout * 3);
+
+    let region = cluster.get_region(b"k1");
+    let put = new_request(
+        region.get_id(),
+        region.get_region_epoch().clone(),
+        vec![new_put_cmd(b"k2", b"v2")],
+        false,
+    );
+    let resp = cluster
+        .call_command_on_leader_with_opts(put, Duration::from_secs(10), None)
+        .unwrap();
+    assert!(!resp.get_header().has_error(), "{:?}", resp);
+    assert_eq!(cluster.must_get(b"k2").unwrap(), b"v2");
+
+    // With a single attempt allowed the stale leader's error is surfaced
+    // instead of being retried away.
+    cluster.clear_send_filters();
+    cluster.must_transfer_leader(1, new_peer(2, 2));
+    cluster.add_send_filter(IsolationFilterFactory::new(2));
+    thread::sleep(election_timeout * 3);
+    let put = new_request(
+        region.get_id(),
+        region.get_region_epoch().clone(),
+        vec![new_put_cmd(b"k3", b"v3")],
+        false,
+    );
+    let resp = cluster
+        .call_command_on_leader_with_opts(put, Duration::from_secs(10), Some(1))
+        .unwrap();
+    assert!(resp.get_header().get_error().has_not_leader(), "{:?}", resp);
+    cluster.clear_send_filters();
+}
+
 fn test_pd_transfer_leader<T: Simulator>(cluster: &mut Cluster<T>) {
     let pd_client = Arc::clone(&cluster.pd_client);
     pd_client.disable_default_operator();
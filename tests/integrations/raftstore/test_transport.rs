@@ -1,5 +1,7 @@
 // Copyright 2016 TiKV Project Authors. Licensed under Apache-2.0.
 
+use std::time::Duration;
+
 use test_raftstore::*;
 
 fn test_partition_write<T: Simulator>(cluster: &mut Cluster<T>) {
@@ -48,6 +50,27 @@ fn test_server_partition_write() {
     test_partition_write(&mut cluster);
 }
 
+#[test]
+fn test_reorder_write() {
+    let mut cluster = new_node_cluster(0, 3);
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    // Writes must still commit with messages shuffled in small batches,
+    // since reordering never drops messages.
+    cluster.add_send_filter(ReorderFilterFactory::new(4, 0xdead_beef));
+    for i in 0..50 {
+        let k = format!("k{:03}", i).into_bytes();
+        cluster.must_put(&k, b"v");
+    }
+    cluster.clear_send_filters();
+
+    for i in 0..50 {
+        let k = format!("k{:03}", i).into_bytes();
+        assert_eq!(cluster.must_get(&k), Some(b"v".to_vec()));
+    }
+}
+
 #[test]
 fn test_secure_connect() {
     let mut cluster = new_server_cluster(0, 3);
@@ -62,3 +85,40 @@ fn test_secure_connect() {
         must_get_equal(&cluster.get_engine(id), key, value);
     }
 }
+
+#[test]
+fn test_partition_heals_on_schedule() {
+    let mut cluster = new_node_cluster(0, 5);
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    let region_id = cluster.get_region_id(b"k1");
+    cluster.must_transfer_leader(region_id, new_peer(1, 1));
+
+    // Cut the leader off from the majority; writes can't commit until the
+    // scheduled heal.
+    let guard = cluster.partition_for(vec![1, 2], vec![3, 4, 5], Duration::from_secs(2));
+    let req = new_request(
+        region_id,
+        cluster.get_region_epoch(region_id),
+        vec![new_put_cmd(b"k2", b"v2")],
+        false,
+    );
+    let resp = cluster.call_command_on_leader_with_opts(req, Duration::from_millis(500), Some(1));
+    assert!(
+        resp.is_err() || resp.unwrap().get_header().has_error(),
+        "write should not commit while partitioned"
+    );
+
+    guard.join();
+    cluster.must_put(b"k3", b"v3");
+    for id in 1..=5 {
+        must_get_equal(&cluster.get_engine(id), b"k3", b"v3");
+    }
+
+    // A canceled partition heals immediately.
+    let guard = cluster.partition_for(vec![1], vec![2, 3, 4, 5], Duration::from_secs(60));
+    guard.cancel();
+    cluster.must_put(b"k4", b"v4");
+    must_get_equal(&cluster.get_engine(1), b"k4", b"v4");
+}
@@ -312,3 +312,56 @@ fn test_gc_bypass_raft() {
         assert!(engine.kv.get_value_cf(CF_WRITE, &key).unwrap().is_none());
     }
 }
+
+// `must_gc_and_compact` packs safe point advancing, GC and range compaction
+// into one call, and `count_write_versions` checks the physical outcome.
+#[test]
+fn test_gc_and_compact_region() {
+    let (mut cluster, leader, ctx) = must_new_cluster_mul(1);
+    cluster.pd_client.disable_default_operator();
+
+    let env = Arc::new(Environment::new(1));
+    let leader_store = leader.get_store_id();
+    let channel = ChannelBuilder::new(env).connect(&cluster.sim.rl().get_addr(leader_store));
+    let client = TikvClient::new(channel);
+
+    let pk = b"k1".to_vec();
+    let value = vec![b'x'; 300];
+    for &start_ts in &[10, 20, 30, 40] {
+        let commit_ts = start_ts + 5;
+        let muts = vec![new_mutation(Op::Put, b"k1", &value)];
+        must_kv_prewrite(&client, ctx.clone(), muts, pk.clone(), start_ts);
+        must_kv_commit(
+            &client,
+            ctx.clone(),
+            vec![pk.clone()],
+            start_ts,
+            commit_ts,
+            commit_ts,
+        );
+    }
+    assert_eq!(cluster.count_write_versions(leader_store, b"k1"), 4);
+
+    cluster.must_gc_and_compact(ctx.get_region_id(), 200);
+
+    // Only the latest version survives the safe point.
+    assert_eq!(cluster.count_write_versions(leader_store, b"k1"), 1);
+}
+
+// The GC worker tracks the largest safe point it has processed a task for,
+// so tests can confirm a safe point reached a store before asserting on
+// reclaimed data.
+#[test]
+fn test_store_gc_safe_point() {
+    let (cluster, leader, _ctx) = must_new_cluster_mul(1);
+    let store_id = leader.get_store_id();
+    assert_eq!(cluster.store_gc_safe_point(store_id), 0);
+
+    let gc_sched = cluster.sim.rl().get_gc_scheduler(store_id).unwrap();
+    sync_gc(&gc_sched, 0, b"k1".to_vec(), b"k2".to_vec(), 123.into()).unwrap();
+    cluster.wait_gc_safe_point_gte(store_id, 123, std::time::Duration::from_secs(5));
+
+    // An older safe point doesn't make the applied one regress.
+    sync_gc(&gc_sched, 0, b"k1".to_vec(), b"k2".to_vec(), 100.into()).unwrap();
+    assert_eq!(cluster.store_gc_safe_point(store_id), 123);
+}